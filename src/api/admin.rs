@@ -0,0 +1,268 @@
+use mongodb::bson::oid::ObjectId;
+use rocket::{serde::json::Json, Route, State};
+use serde::Serialize;
+
+use crate::{
+    database::DatabaseHandler,
+    models::{
+        audit::{AuditEntry, ModerationAction},
+        user::{Role, User},
+    },
+    storage::ImageStorage,
+};
+
+use super::{
+    guards::{Admin, Moderator},
+    id_of, parse_object_id,
+    reports::{self, ReportedPost},
+};
+
+/// How many entries of the audit trail are shown at once.
+const AUDIT_TRAIL_LENGTH: i64 = 100;
+
+/// A user as shown to admins. This leaves out the password hash.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct UserSummary {
+    pub id: String,
+    pub name: String,
+    pub role: Role,
+    pub suspended: bool,
+}
+
+impl From<User> for UserSummary {
+    fn from(user: User) -> Self {
+        UserSummary {
+            id: user.id.map(|id| id.to_string()).unwrap_or_default(),
+            name: user.name,
+            role: user.role,
+            suspended: user.suspended,
+        }
+    }
+}
+
+/// An entry of the audit trail with names instead of ids, so that it can be shown to admins.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct AuditEntryView {
+    pub moderator: String,
+    pub action: ModerationAction,
+    pub target: String,
+    pub timestamp: String,
+}
+
+/// Get the name of a user, or the id if the user no longer exists.
+fn name_or_id(db: &DatabaseHandler, id: &ObjectId) -> Result<String, String> {
+    Ok(db
+        .find_user_by_id(id)?
+        .map(|user| user.name)
+        .unwrap_or_else(|| id.to_string()))
+}
+
+/// List all users.
+pub fn list_users(db: &DatabaseHandler) -> Result<Vec<UserSummary>, String> {
+    Ok(db
+        .find_all_users()?
+        .into_iter()
+        .map(UserSummary::from)
+        .collect())
+}
+
+/// Check that a user may act on another user, which they may only do to users of a lower role.
+/// This keeps admins from suspending each other, down to the last one. Only `bread-admin` can do that.
+fn check_outranks(actor: &User, target: &User) -> Result<(), String> {
+    if actor.id == target.id {
+        return Err("You can't do that to yourself!".to_string());
+    }
+    if target.role >= actor.role {
+        return Err("You can only do that to users with a lower role than yours!".to_string());
+    }
+
+    Ok(())
+}
+
+/// Suspend or unsuspend a user of a lower role and record it in the audit trail.
+pub fn set_suspended(
+    db: &DatabaseHandler,
+    admin: &User,
+    user_id: &str,
+    suspended: bool,
+) -> Result<(), String> {
+    let admin_id = id_of(admin)?;
+    let user_id = parse_object_id(user_id)?;
+
+    let user = db
+        .find_user_by_id(&user_id)?
+        .ok_or_else(|| "No such user!".to_string())?;
+    check_outranks(admin, &user)?;
+
+    if db.set_user_suspended(&user_id, suspended)?.matched_count == 0 {
+        return Err("No such user!".to_string());
+    }
+
+    let action = if suspended {
+        ModerationAction::SuspendUser
+    } else {
+        ModerationAction::UnsuspendUser
+    };
+    db.save_audit_entry(&AuditEntry::create(admin_id, action, user_id))?;
+
+    Ok(())
+}
+
+/// Remove a post along with its image and record it in the audit trail.
+pub fn remove_post(
+    db: &DatabaseHandler,
+    storage: &ImageStorage,
+    moderator: &User,
+    post_id: &str,
+) -> Result<(), String> {
+    let moderator_id = id_of(moderator)?;
+    let post_id = parse_object_id(post_id)?;
+
+    let post = db
+        .remove_post(&post_id)?
+        .ok_or_else(|| "Post not found!".to_string())?;
+
+    db.save_audit_entry(&AuditEntry::create(
+        moderator_id,
+        ModerationAction::RemovePost,
+        post_id,
+    ))?;
+
+    // The post is gone either way, so an image that can't be deleted is only logged.
+    if let Some(image) = &post.image {
+        if let Err(err) = storage.delete(image) {
+            error!(
+                "Could not delete image {} of removed post {}: {}",
+                image, post_id, err
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Get the latest entries of the audit trail, with the names of the users involved.
+pub fn audit_trail(db: &DatabaseHandler) -> Result<Vec<AuditEntryView>, String> {
+    db.find_audit_entries(AUDIT_TRAIL_LENGTH)?
+        .into_iter()
+        .map(|entry| {
+            let target = match entry.action {
+                ModerationAction::SuspendUser
+                | ModerationAction::UnsuspendUser
+                | ModerationAction::ChangeRole
+                | ModerationAction::DeleteUser => name_or_id(db, &entry.target)?,
+                ModerationAction::RemovePost | ModerationAction::DismissReports => {
+                    entry.target.to_string()
+                }
+            };
+
+            Ok(AuditEntryView {
+                moderator: match &entry.moderator {
+                    Some(moderator) => name_or_id(db, moderator)?,
+                    None => "bread-admin".to_string(),
+                },
+                action: entry.action,
+                target,
+                timestamp: entry
+                    .timestamp
+                    .try_to_rfc3339_string()
+                    .map_err(|err| err.to_string())?,
+            })
+        })
+        .collect()
+}
+
+#[get("/users")]
+fn users(db: &State<DatabaseHandler>, _admin: Admin) -> Result<Json<Vec<UserSummary>>, String> {
+    list_users(db).map(Json)
+}
+
+#[post("/users/<user_id>/suspend")]
+fn suspend(db: &State<DatabaseHandler>, admin: Admin, user_id: &str) -> Result<(), String> {
+    set_suspended(db, &admin.0, user_id, true)
+}
+
+#[post("/users/<user_id>/unsuspend")]
+fn unsuspend(db: &State<DatabaseHandler>, admin: Admin, user_id: &str) -> Result<(), String> {
+    set_suspended(db, &admin.0, user_id, false)
+}
+
+#[post("/posts/<post_id>/remove")]
+fn remove(
+    db: &State<DatabaseHandler>,
+    storage: &State<ImageStorage>,
+    moderator: Moderator,
+    post_id: &str,
+) -> Result<(), String> {
+    remove_post(db, storage, &moderator.0, post_id)
+}
+
+#[get("/reports")]
+fn report_queue(
+    db: &State<DatabaseHandler>,
+    _moderator: Moderator,
+) -> Result<Json<Vec<ReportedPost>>, String> {
+    reports::report_queue(db).map(Json)
+}
+
+#[post("/reports/<post_id>/dismiss")]
+fn dismiss_reports(
+    db: &State<DatabaseHandler>,
+    moderator: Moderator,
+    post_id: &str,
+) -> Result<(), String> {
+    reports::dismiss_reports(db, &moderator.0, post_id)
+}
+
+#[post("/reports/<post_id>/remove")]
+fn remove_reported_post(
+    db: &State<DatabaseHandler>,
+    storage: &State<ImageStorage>,
+    moderator: Moderator,
+    post_id: &str,
+) -> Result<(), String> {
+    reports::remove_reported_post(db, storage, &moderator.0, post_id)
+}
+
+#[get("/audit")]
+fn audit(db: &State<DatabaseHandler>, _admin: Admin) -> Result<Json<Vec<AuditEntryView>>, String> {
+    audit_trail(db).map(Json)
+}
+
+pub fn get_admin_routes() -> Vec<Route> {
+    routes![
+        users,
+        suspend,
+        unsuspend,
+        remove,
+        report_queue,
+        dismiss_reports,
+        remove_reported_post,
+        audit
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::user::test_user;
+
+    use super::*;
+
+    #[test]
+    fn only_lower_roles_can_be_acted_on() {
+        let admin = test_user("Admin", Role::Admin);
+        let other_admin = test_user("Other admin", Role::Admin);
+        let moderator = test_user("Moderator", Role::Moderator);
+        let user = test_user("User", Role::User);
+
+        assert!(check_outranks(&admin, &moderator).is_ok());
+        assert!(check_outranks(&admin, &user).is_ok());
+        assert!(check_outranks(&moderator, &user).is_ok());
+
+        assert!(check_outranks(&admin, &admin).is_err());
+        assert!(check_outranks(&admin, &other_admin).is_err());
+        assert!(check_outranks(&moderator, &admin).is_err());
+    }
+}
@@ -0,0 +1,338 @@
+use mongodb::bson::{oid::ObjectId, DateTime};
+use rocket::{form::Form, serde::json::Json, Route, State};
+use serde::Serialize;
+
+use crate::{
+    database::{pagination::Page, DatabaseHandler},
+    models::{
+        conversation::{Conversation, Envelope, Message},
+        user::User,
+    },
+};
+
+use super::{devices::is_base64, guards::AuthUser, id_of, parse_object_id, PublicUser};
+
+/// How many messages are shown per page of a conversation.
+pub const MESSAGES_PER_PAGE: i64 = 30;
+
+/// The longest ciphertext that is accepted for one device, base64 encoded.
+pub const MAX_CIPHERTEXT_LENGTH: usize = 64 * 1024;
+
+/// A message, encrypted by the sender for each device that should be able to read it.
+#[derive(FromForm)]
+pub struct MessageForm {
+    pub envelopes: Vec<Envelope>,
+}
+
+/// A conversation as listed in a user's inbox.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct ConversationSummary {
+    pub id: String,
+    /// The user on the other end of the conversation.
+    pub with: PublicUser,
+    pub last_message_at: Option<DateTime>,
+    pub unread: bool,
+}
+
+/// A page of a conversation's messages, newest first.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct ConversationHistory {
+    pub with: PublicUser,
+    pub messages: Page<Message>,
+    /// When the other user last read the conversation, unless they have turned read receipts off.
+    pub read_by_other_at: Option<DateTime>,
+}
+
+/// Check if two users follow each other and haven't blocked each other.
+/// Only mutual followers can talk to each other.
+pub fn are_mutual_followers(
+    db: &DatabaseHandler,
+    a: &ObjectId,
+    b: &ObjectId,
+) -> Result<bool, String> {
+    Ok(db.is_following(a, b)? && db.is_following(b, a)? && !db.is_blocked_between(a, b)?)
+}
+
+/// Get all users that a user can start a conversation with, sorted by name.
+pub fn find_mutual_followers(db: &DatabaseHandler, user: &User) -> Result<Vec<User>, String> {
+    let user_id = id_of(user)?;
+    let followers = db.find_followers(&user_id)?;
+    let mutuals: Vec<_> = db
+        .find_followees(&user_id)?
+        .into_iter()
+        .filter(|followee| followers.contains(followee))
+        .collect();
+
+    db.find_users_by_ids(&mutuals)
+}
+
+/// Get the conversation between a user and another user, starting it if needed.
+pub fn start_conversation(
+    db: &DatabaseHandler,
+    user: &User,
+    other_id: &str,
+) -> Result<Conversation, String> {
+    let user_id = id_of(user)?;
+    let other_id = parse_object_id(other_id)?;
+
+    if user_id == other_id {
+        return Err("You can't talk to yourself!".to_string());
+    }
+
+    if !are_mutual_followers(db, &user_id, &other_id)? {
+        return Err("You can only talk to people who follow you back!".to_string());
+    }
+
+    db.find_or_create_conversation(&user_id, &other_id)
+}
+
+/// Get a conversation that the user takes part in.
+fn conversation_of(
+    db: &DatabaseHandler,
+    user_id: &ObjectId,
+    conversation_id: &str,
+) -> Result<Conversation, String> {
+    match db.find_conversation_by_id(&parse_object_id(conversation_id)?)? {
+        Some(conversation) if conversation.has_participant(user_id) => Ok(conversation),
+        _ => Err("Conversation not found!".to_string()),
+    }
+}
+
+/// List the conversations a user takes part in, the most recently active first.
+pub fn inbox(db: &DatabaseHandler, user: &User) -> Result<Vec<ConversationSummary>, String> {
+    let user_id = id_of(user)?;
+    let conversations = db.find_conversations_of(&user_id)?;
+    let other_ids: Vec<_> = conversations
+        .iter()
+        .map(|conversation| conversation.other(&user_id))
+        .collect();
+    let others = db.find_users_by_ids(&other_ids)?;
+
+    Ok(conversations
+        .into_iter()
+        .filter_map(|conversation| {
+            // Conversations with deleted users are left out.
+            let other_id = conversation.other(&user_id);
+            let other = others.iter().find(|other| other.id == Some(other_id))?;
+
+            Some(ConversationSummary {
+                id: conversation.id?.to_string(),
+                with: PublicUser::from(other),
+                last_message_at: conversation.last_message_at,
+                unread: conversation.is_unread_by(&user_id),
+            })
+        })
+        .collect())
+}
+
+/// Get a page of a conversation's messages and mark the conversation as read.
+pub fn read_conversation(
+    db: &DatabaseHandler,
+    user: &User,
+    conversation_id: &str,
+    cursor: Option<&str>,
+) -> Result<ConversationHistory, String> {
+    let user_id = id_of(user)?;
+    let conversation = conversation_of(db, &user_id, conversation_id)?;
+    let other = db
+        .find_user_by_id(&conversation.other(&user_id))?
+        .ok_or_else(|| "Conversation not found!".to_string())?;
+
+    let messages = db.find_messages(
+        &conversation.id.ok_or("Conversation not found!")?,
+        cursor,
+        MESSAGES_PER_PAGE,
+    )?;
+    db.mark_conversation_read(&conversation, &user_id)?;
+
+    let read_by_other_at = if other.preferences.send_read_receipts {
+        conversation.read_at(&conversation.other(&user_id))
+    } else {
+        None
+    };
+
+    Ok(ConversationHistory {
+        with: PublicUser::from(&other),
+        messages,
+        read_by_other_at,
+    })
+}
+
+/// Check the envelopes of a message and make it ready to be stored.
+/// Each envelope must be addressed to one of `devices`, at most once, and hold base64 encoded ciphertext.
+/// The ciphertext is stored exactly as it was sent, the server never tries to decrypt it.
+pub fn seal_message(
+    conversation: ObjectId,
+    sender: ObjectId,
+    envelopes: Vec<Envelope>,
+    devices: &[&str],
+) -> Result<Message, String> {
+    if envelopes.is_empty() {
+        return Err("A message can't be empty!".to_string());
+    }
+
+    for (i, envelope) in envelopes.iter().enumerate() {
+        if !devices.contains(&envelope.device.as_str()) {
+            return Err(
+                "A message can only be sent to the devices in the conversation!".to_string(),
+            );
+        }
+
+        if envelopes[..i]
+            .iter()
+            .any(|other| other.device == envelope.device)
+        {
+            return Err("A message can only be sent to each device once!".to_string());
+        }
+
+        if envelope.ciphertext.len() > MAX_CIPHERTEXT_LENGTH || !is_base64(&envelope.ciphertext) {
+            return Err("Messages must be encrypted!".to_string());
+        }
+    }
+
+    Ok(Message::create(conversation, sender, envelopes))
+}
+
+/// Send a message in a conversation. The participants must still follow each other.
+pub fn send_message(
+    db: &DatabaseHandler,
+    sender: &User,
+    conversation_id: &str,
+    envelopes: Vec<Envelope>,
+) -> Result<(), String> {
+    let sender_id = id_of(sender)?;
+    let conversation = conversation_of(db, &sender_id, conversation_id)?;
+    let recipient_id = conversation.other(&sender_id);
+
+    if !are_mutual_followers(db, &sender_id, &recipient_id)? {
+        return Err("You can only talk to people who follow you back!".to_string());
+    }
+
+    let recipient = db
+        .find_user_by_id(&recipient_id)?
+        .ok_or_else(|| "Conversation not found!".to_string())?;
+    let devices: Vec<&str> = sender
+        .devices
+        .iter()
+        .chain(&recipient.devices)
+        .map(|device| device.id.as_str())
+        .collect();
+
+    db.save_message(&seal_message(
+        conversation.id.ok_or("Conversation not found!")?,
+        sender_id,
+        envelopes,
+        &devices,
+    )?)?;
+
+    Ok(())
+}
+
+#[post("/conversations/<user_id>")]
+fn start(
+    db: &State<DatabaseHandler>,
+    user: AuthUser,
+    user_id: &str,
+) -> Result<Json<Conversation>, String> {
+    start_conversation(db, &user.0, user_id).map(Json)
+}
+
+#[get("/conversations")]
+fn conversations(
+    db: &State<DatabaseHandler>,
+    user: AuthUser,
+) -> Result<Json<Vec<ConversationSummary>>, String> {
+    inbox(db, &user.0).map(Json)
+}
+
+#[get("/conversations/<conversation_id>/messages?<cursor>")]
+fn messages(
+    db: &State<DatabaseHandler>,
+    user: AuthUser,
+    conversation_id: &str,
+    cursor: Option<&str>,
+) -> Result<Json<ConversationHistory>, String> {
+    read_conversation(db, &user.0, conversation_id, cursor).map(Json)
+}
+
+#[post("/conversations/<conversation_id>/messages", data = "<message>")]
+fn send(
+    db: &State<DatabaseHandler>,
+    user: AuthUser,
+    conversation_id: &str,
+    message: Form<MessageForm>,
+) -> Result<(), String> {
+    send_message(db, &user.0, conversation_id, message.into_inner().envelopes)
+}
+
+pub fn get_conversation_routes() -> Vec<Route> {
+    routes![start, conversations, messages, send]
+}
+
+#[cfg(test)]
+mod tests {
+    use mongodb::bson::to_document;
+
+    use super::*;
+
+    fn envelope(device: &str, ciphertext: &str) -> Envelope {
+        Envelope {
+            device: device.to_string(),
+            ciphertext: ciphertext.to_string(),
+        }
+    }
+
+    /// What gets stored is only who sent the message and the ciphertext, exactly as it was sent.
+    #[test]
+    fn only_ciphertext_is_stored() {
+        let envelopes = vec![
+            envelope("phone", "3q2+78r+ur4BAgMEBQYHCAkKCwwNDg8Q"),
+            envelope("laptop", "AAECAwQFBgcICQoLDA0ODxAREhMUFRYX"),
+        ];
+        let message = seal_message(
+            ObjectId::new(),
+            ObjectId::new(),
+            envelopes.clone(),
+            &["phone", "laptop"],
+        )
+        .unwrap();
+
+        let document = to_document(&message).unwrap();
+        let mut keys: Vec<_> = document.keys().map(String::as_str).collect();
+        keys.sort();
+        assert_eq!(keys, ["conversation", "created_at", "envelopes", "sender"]);
+        assert_eq!(message.envelopes, envelopes);
+    }
+
+    /// Plain text isn't base64, so it is turned away instead of being stored.
+    #[test]
+    fn plaintext_is_rejected() {
+        let result = seal_message(
+            ObjectId::new(),
+            ObjectId::new(),
+            vec![envelope("phone", "Hi! How are you doing?")],
+            &["phone"],
+        );
+
+        assert!(result.is_err());
+    }
+
+    /// Envelopes can only be addressed to the devices in the conversation, once each.
+    #[test]
+    fn envelopes_must_match_devices() {
+        let ciphertext = "3q2+78r+ur4BAgMEBQYHCAkKCwwNDg8Q";
+        let seal =
+            |envelopes| seal_message(ObjectId::new(), ObjectId::new(), envelopes, &["phone"]);
+
+        assert!(seal(vec![]).is_err());
+        assert!(seal(vec![envelope("stranger", ciphertext)]).is_err());
+        assert!(seal(vec![
+            envelope("phone", ciphertext),
+            envelope("phone", ciphertext)
+        ])
+        .is_err());
+        assert!(seal(vec![envelope("phone", ciphertext)]).is_ok());
+    }
+}
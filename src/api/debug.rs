@@ -3,14 +3,19 @@ use rocket::{serde::json::Json, Route, State};
 use serde::Deserialize;
 
 use crate::{
+    config::BreadConfig,
     database::DatabaseHandler,
     models::{post::Post, user::User},
 };
 
+use super::guards::Admin;
+
+// These routes are only compiled in with the "debug-routes" feature, and even then only admins may use them.
+
 #[get("/test-user")]
-pub fn test_get_user() -> Result<Json<User>, String> {
-    User::create("Foo".to_string(), "Bar".to_string())
-        .map(|user| Json(user))
+pub fn test_get_user(_admin: Admin, config: &State<BreadConfig>) -> Result<Json<User>, String> {
+    User::create("Foo".to_string(), "Bar".to_string(), &config.argon2)
+        .map(Json)
         .map_err(|err| err.to_string())
 }
 
@@ -23,10 +28,16 @@ pub struct CreateUser<'r> {
 
 #[post("/test-user", data = "<input>")]
 pub fn test_post_user(
+    _admin: Admin,
     input: Json<CreateUser<'_>>,
     db: &State<DatabaseHandler>,
+    config: &State<BreadConfig>,
 ) -> Result<Json<User>, String> {
-    let user = match User::create(input.username.to_string(), input.password.to_string()) {
+    let user = match User::create(
+        input.username.to_string(),
+        input.password.to_string(),
+        &config.argon2,
+    ) {
         Ok(user) => user,
         Err(_) => return Err("Error while creating user!".to_string()),
     };
@@ -45,7 +56,7 @@ pub fn test_post_user(
 }
 
 #[get("/test-post")]
-pub fn test_get_post() -> Json<Post> {
+pub fn test_get_post(_admin: Admin) -> Json<Post> {
     Json(Post::create(
         ObjectId::new(),
         Some("Lorem ipsum dolor sit amet consectetur adipis.".to_string()),
@@ -0,0 +1,134 @@
+use mongodb::bson::{Binary, DateTime};
+use rocket::{form::Form, serde::json::Json, Route, State};
+use serde::Serialize;
+
+use crate::{
+    database::DatabaseHandler,
+    models::user::{Device, User},
+};
+
+use super::{conversations::are_mutual_followers, guards::AuthUser, id_of, parse_object_id};
+
+/// How many devices a user can have at once.
+pub const MAX_DEVICES: usize = 10;
+
+/// The longest public key that is accepted, base64 encoded.
+const MAX_PUBLIC_KEY_LENGTH: usize = 1024;
+
+/// The public key of a device that is being registered.
+#[derive(FromForm)]
+struct DeviceForm {
+    public_key: String,
+}
+
+/// A device's public key, as handed to the people who want to send its owner a message.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct DeviceKey {
+    pub device: String,
+    pub public_key: String,
+}
+
+/// Check if a string is valid base64.
+/// The server never decodes keys or ciphertext, but garbage is still rejected.
+pub fn is_base64(input: &str) -> bool {
+    !input.is_empty() && Binary::from_base64(input, None).is_ok()
+}
+
+/// Check if a device id is short and only uses letters, digits, `-` and `_`.
+fn is_valid_device_id(device_id: &str) -> bool {
+    (1..=64).contains(&device_id.len())
+        && device_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Register a device's public key, replacing the key if the device is already registered.
+pub fn register_device(
+    db: &DatabaseHandler,
+    user: &User,
+    device_id: &str,
+    public_key: &str,
+) -> Result<(), String> {
+    if !is_valid_device_id(device_id) {
+        return Err("Invalid device id!".to_string());
+    }
+
+    if public_key.len() > MAX_PUBLIC_KEY_LENGTH || !is_base64(public_key) {
+        return Err("Invalid public key!".to_string());
+    }
+
+    let is_new = !user.devices.iter().any(|device| device.id == device_id);
+    if is_new && user.devices.len() >= MAX_DEVICES {
+        return Err("You have too many devices, remove one first.".to_string());
+    }
+
+    db.save_device(
+        &id_of(user)?,
+        &Device {
+            id: device_id.to_string(),
+            public_key: public_key.to_string(),
+            added_at: DateTime::now(),
+        },
+    )?;
+
+    Ok(())
+}
+
+/// Get the public keys of a user's devices.
+/// Only the user themselves and the people they can talk to can get them.
+/// The keys are handed out as they were uploaded. Nothing vouches for them, and nobody is told when they change.
+pub fn public_keys(
+    db: &DatabaseHandler,
+    user: &User,
+    owner_id: &str,
+) -> Result<Vec<DeviceKey>, String> {
+    let user_id = id_of(user)?;
+    let owner_id = parse_object_id(owner_id)?;
+
+    if user_id != owner_id && !are_mutual_followers(db, &user_id, &owner_id)? {
+        return Err("You can only get the keys of people who follow you back!".to_string());
+    }
+
+    let owner = db
+        .find_user_by_id(&owner_id)?
+        .ok_or_else(|| "User not found!".to_string())?;
+
+    Ok(owner
+        .devices
+        .into_iter()
+        .map(|device| DeviceKey {
+            device: device.id,
+            public_key: device.public_key,
+        })
+        .collect())
+}
+
+#[put("/devices/<device_id>", data = "<device>")]
+fn register(
+    db: &State<DatabaseHandler>,
+    user: AuthUser,
+    device_id: &str,
+    device: Form<DeviceForm>,
+) -> Result<(), String> {
+    register_device(db, &user.0, device_id, &device.public_key)
+}
+
+#[delete("/devices/<device_id>")]
+fn remove(db: &State<DatabaseHandler>, user: AuthUser, device_id: &str) -> Result<(), String> {
+    db.delete_device(&id_of(&user.0)?, device_id)?;
+    Ok(())
+}
+
+#[get("/keys/<user_id>")]
+fn keys(
+    db: &State<DatabaseHandler>,
+    user: AuthUser,
+    user_id: &str,
+) -> Result<Json<Vec<DeviceKey>>, String> {
+    public_keys(db, &user.0, user_id).map(Json)
+}
+
+pub fn get_device_routes() -> Vec<Route> {
+    routes![register, remove, keys]
+}
@@ -0,0 +1,163 @@
+use rocket::{
+    http::Status,
+    outcome::try_outcome,
+    request::{FromRequest, Outcome, Request},
+};
+
+use crate::{
+    database::DatabaseHandler,
+    logging,
+    models::user::{Role, User},
+};
+
+use super::{
+    token::Claims,
+    wellbeing::{self, ActivityLimiter},
+};
+
+/// A request guard for a logged in user that isn't suspended.
+/// The user is read from the database, so changes to the user's role or suspension apply right away.
+pub struct AuthUser(pub User);
+
+/// A request guard that only lets through moderators and admins.
+pub struct Moderator(pub User);
+
+/// A request guard that only lets through admins.
+pub struct Admin(pub User);
+
+/// Make sure a user isn't suspended and has at least the given role.
+fn check_access(user: &User, role: Role) -> Result<(), (Status, String)> {
+    if user.suspended {
+        return Err((Status::Forbidden, "This account is suspended!".to_string()));
+    }
+
+    if user.role < role {
+        return Err((
+            Status::Forbidden,
+            "You are not allowed to do this!".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Get the logged in user of a request and make sure they have at least the given role.
+async fn user_with_role(request: &Request<'_>, role: Role) -> Outcome<User, String> {
+    let claims = try_outcome!(request.guard::<Claims>().await);
+
+    let db = match request.rocket().state::<DatabaseHandler>() {
+        Some(db) => db,
+        None => {
+            return Outcome::Error((
+                Status::InternalServerError,
+                "No database connection!".to_string(),
+            ))
+        }
+    };
+
+    // Tokens from before they held ids hold names, which are no longer trusted.
+    let user_id = match claims.user_id() {
+        Ok(user_id) => user_id,
+        Err(err) => return Outcome::Error((Status::Unauthorized, err)),
+    };
+
+    let user = match db.find_user_by_id(&user_id) {
+        Ok(Some(user)) => user,
+        Ok(None) => return Outcome::Error((Status::Unauthorized, "No such user!".to_string())),
+        Err(err) => return Outcome::Error((Status::InternalServerError, err)),
+    };
+
+    if let Err(err) = check_access(&user, role) {
+        return Outcome::Error(err);
+    }
+
+    if let Some(id) = user.id {
+        logging::set_request_user(request, id);
+    }
+
+    // Failing to note the activity only makes the user's usage summary a little off, so the request goes on.
+    match request.rocket().state::<ActivityLimiter>() {
+        Some(limiter) => {
+            if let Err(err) = wellbeing::record_activity(db, limiter, &user) {
+                warn!("Could not record activity: {}", err);
+            }
+        }
+        None => warn!("Activity isn't recorded without an activity limiter!"),
+    }
+
+    Outcome::Success(user)
+}
+
+#[rocket::async_trait]
+impl<'a> FromRequest<'a> for AuthUser {
+    type Error = String;
+
+    async fn from_request(request: &'a Request<'_>) -> Outcome<Self, Self::Error> {
+        user_with_role(request, Role::User).await.map(AuthUser)
+    }
+}
+
+#[rocket::async_trait]
+impl<'a> FromRequest<'a> for Moderator {
+    type Error = String;
+
+    async fn from_request(request: &'a Request<'_>) -> Outcome<Self, Self::Error> {
+        user_with_role(request, Role::Moderator)
+            .await
+            .map(Moderator)
+    }
+}
+
+#[rocket::async_trait]
+impl<'a> FromRequest<'a> for Admin {
+    type Error = String;
+
+    async fn from_request(request: &'a Request<'_>) -> Outcome<Self, Self::Error> {
+        user_with_role(request, Role::Admin).await.map(Admin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::user::test_user;
+
+    use super::*;
+
+    fn user(role: Role) -> User {
+        test_user("Baker", role)
+    }
+
+    #[test]
+    fn guards_reject_lower_roles() {
+        // What the `AuthUser`, `Moderator` and `Admin` guards ask for.
+        let guards = [Role::User, Role::Moderator, Role::Admin];
+
+        for role in guards {
+            for guard in guards {
+                let result = check_access(&user(role), guard);
+                if role < guard {
+                    assert_eq!(
+                        result.unwrap_err().0,
+                        Status::Forbidden,
+                        "{:?} as {:?}",
+                        role,
+                        guard
+                    );
+                } else {
+                    assert!(result.is_ok(), "{:?} as {:?}", role, guard);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn guards_reject_suspended_users() {
+        let mut admin = user(Role::Admin);
+        admin.suspended = true;
+
+        assert_eq!(
+            check_access(&admin, Role::User).unwrap_err().0,
+            Status::Forbidden
+        );
+    }
+}
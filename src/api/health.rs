@@ -0,0 +1,103 @@
+use rocket::{http::Status, serde::json::Json, Route, State};
+use serde::Serialize;
+
+use crate::{database::DatabaseHandler, storage::ImageStorage};
+
+/// Whether Bread, or a part of it, works.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(crate = "rocket::serde", rename_all = "lowercase")]
+pub enum HealthStatus {
+    Up,
+    Down,
+}
+
+/// Whether Bread is running at all.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct Liveness {
+    pub status: HealthStatus,
+}
+
+/// Whether Bread can serve requests, and which of its parts don't work if it can't.
+/// Why a part is down is only logged, since anyone can ask for this.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct Readiness {
+    pub status: HealthStatus,
+    pub database: HealthStatus,
+    pub image_storage: HealthStatus,
+}
+
+impl Readiness {
+    /// Bread is only ready if all of its parts are.
+    fn new(database: HealthStatus, image_storage: HealthStatus) -> Self {
+        let status = if [database, image_storage].contains(&HealthStatus::Down) {
+            HealthStatus::Down
+        } else {
+            HealthStatus::Up
+        };
+
+        Readiness {
+            status,
+            database,
+            image_storage,
+        }
+    }
+}
+
+/// Turn the result of a check into a status, logging why the check failed.
+fn check(component: &str, result: Result<(), String>) -> HealthStatus {
+    match result {
+        Ok(()) => HealthStatus::Up,
+        Err(err) => {
+            warn!("Readiness check of the {} failed: {}", component, err);
+            HealthStatus::Down
+        }
+    }
+}
+
+/// Always succeeds while Bread is running, so that it is only restarted if it stops responding.
+#[get("/health/live")]
+fn live() -> Json<Liveness> {
+    Json(Liveness {
+        status: HealthStatus::Up,
+    })
+}
+
+/// Succeeds if the database can be reached and images can be saved, and fails with 503 otherwise,
+/// so that no requests are sent to Bread while it can't serve them.
+#[get("/health/ready")]
+fn ready(db: &State<DatabaseHandler>, storage: &State<ImageStorage>) -> (Status, Json<Readiness>) {
+    let readiness = Readiness::new(
+        check("database", db.ping()),
+        check("image storage", storage.check_writable()),
+    );
+
+    let status = match readiness.status {
+        HealthStatus::Up => Status::Ok,
+        HealthStatus::Down => Status::ServiceUnavailable,
+    };
+
+    (status, Json(readiness))
+}
+
+pub fn get_health_routes() -> Vec<Route> {
+    routes![live, ready]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ready_only_if_every_part_is_up() {
+        let up = Readiness::new(HealthStatus::Up, HealthStatus::Up);
+        assert_eq!(up.status, HealthStatus::Up);
+
+        let database_down = Readiness::new(HealthStatus::Down, HealthStatus::Up);
+        assert_eq!(database_down.status, HealthStatus::Down);
+
+        let storage_down = Readiness::new(HealthStatus::Up, HealthStatus::Down);
+        assert_eq!(storage_down.status, HealthStatus::Down);
+    }
+}
@@ -0,0 +1,91 @@
+use rocket::{fs::NamedFile, http::Status, Route, State};
+
+use crate::{
+    database::DatabaseHandler,
+    models::{post::Post, user::User},
+    storage::ImageStorage,
+};
+
+use super::{can_see_posts_of, guards::AuthUser, id_of};
+
+/// Check if a viewer may see the image of a post, given whether they may see the author's posts at all.
+/// Expired posts are gone for everyone, and hidden posts can only be seen by their author and moderators.
+fn image_is_visible(post: &Post, viewer: &User, sees_author: bool) -> bool {
+    if post.is_expired() {
+        return false;
+    }
+
+    if post.hidden && !viewer.sees_hidden_posts_of(&post.author) {
+        return false;
+    }
+
+    sees_author
+}
+
+/// Serve the image of a post to those who may see the post.
+/// Images that can't be seen are answered with 404, so that nobody can tell they exist.
+#[get("/<name>")]
+async fn image(
+    db: &State<DatabaseHandler>,
+    storage: &State<ImageStorage>,
+    user: AuthUser,
+    name: &str,
+) -> Result<NamedFile, Status> {
+    let internal_error = |err: String| {
+        error!("Could not serve an image: {}", err);
+        Status::InternalServerError
+    };
+
+    let post = db
+        .find_post_by_image(name)
+        .map_err(internal_error)?
+        .ok_or(Status::NotFound)?;
+    let viewer_id = id_of(&user.0).map_err(internal_error)?;
+    let sees_author = can_see_posts_of(db, &viewer_id, &post.author).map_err(internal_error)?;
+
+    if !image_is_visible(&post, &user.0, sees_author) {
+        return Err(Status::NotFound);
+    }
+
+    let path = storage.path(name).map_err(|_| Status::NotFound)?;
+    NamedFile::open(path).await.map_err(|_| Status::NotFound)
+}
+
+pub fn get_image_routes() -> Vec<Route> {
+    routes![image]
+}
+
+#[cfg(test)]
+mod tests {
+    use mongodb::bson::DateTime;
+
+    use crate::models::user::{test_user, Role};
+
+    use super::*;
+
+    fn user(role: Role) -> User {
+        test_user("Viewer", role)
+    }
+
+    #[test]
+    fn images_follow_post_visibility() {
+        let viewer = user(Role::User);
+        let author = user(Role::User);
+        let moderator = user(Role::Moderator);
+        let mut post = Post::create(author.id.unwrap(), None, Some("image.png".to_string()));
+
+        assert!(image_is_visible(&post, &viewer, true));
+        // E.g. a private author the viewer doesn't follow, or one who has blocked them.
+        assert!(!image_is_visible(&post, &viewer, false));
+
+        post.hidden = true;
+        assert!(!image_is_visible(&post, &viewer, true));
+        assert!(image_is_visible(&post, &author, true));
+        assert!(image_is_visible(&post, &moderator, true));
+
+        post.hidden = false;
+        post.expires_at = Some(DateTime::from_millis(0));
+        assert!(!image_is_visible(&post, &viewer, true));
+        assert!(!image_is_visible(&post, &author, true));
+    }
+}
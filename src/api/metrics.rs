@@ -0,0 +1,51 @@
+use rocket::{http::ContentType, Route, State};
+
+use crate::metrics::Metrics;
+
+/// All metrics, in the text format that Prometheus scrapes.
+/// This is mounted on its own port instead if `metrics_port` is set.
+#[get("/metrics")]
+fn metrics(metrics: &State<Metrics>) -> (ContentType, String) {
+    let content_type = ContentType::new("text", "plain").with_params(("version", "0.0.4"));
+    (content_type, metrics.render())
+}
+
+pub fn get_metrics_routes() -> Vec<Route> {
+    routes![metrics]
+}
+
+#[cfg(test)]
+mod tests {
+    use rocket::{http::Status, local::blocking::Client};
+
+    use super::*;
+    use crate::metrics::RequestMetrics;
+
+    /// Requests are counted by their route, and requests that match no route are counted together.
+    #[test]
+    fn requests_are_counted_by_route() {
+        let rocket = rocket::build()
+            .mount("/", get_metrics_routes())
+            .manage(Metrics::default())
+            .attach(RequestMetrics);
+        let client = Client::tracked(rocket).expect("Could not build Rocket!");
+
+        assert_eq!(client.get("/metrics").dispatch().status(), Status::Ok);
+        assert_eq!(client.get("/made/up").dispatch().status(), Status::NotFound);
+        let response = client.get("/metrics").dispatch();
+
+        assert_eq!(
+            response
+                .content_type()
+                .map(|content_type| content_type.to_string()),
+            Some("text/plain; version=0.0.4".to_string())
+        );
+        let body = response.into_string().unwrap_or_default();
+        assert!(body.contains(
+            "bread_http_requests_total{method=\"GET\",route=\"/metrics\",status=\"200\"} 1"
+        ));
+        assert!(body.contains(
+            "bread_http_requests_total{method=\"GET\",route=\"unmatched\",status=\"404\"} 1"
+        ));
+    }
+}
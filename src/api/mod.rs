@@ -1,25 +1,48 @@
-use jsonwebtoken::Header;
-use rocket::{
-    form::Form,
-    fs::TempFile,
-    http::{Cookie, CookieJar},
-    serde::json::Json,
-    Route, State,
-};
+use std::collections::HashMap;
+
+use mongodb::bson::oid::ObjectId;
+use rocket::{form::Form, fs::TempFile, http::CookieJar, serde::json::Json, Route, State};
+use serde::Serialize;
 
-use crate::{database::DatabaseHandler, models::user::User};
+use crate::{
+    classifier::{CrisisResponse, PostClassifier},
+    config::BreadConfig,
+    database::{pagination::Page, DatabaseHandler},
+    metrics::{Event, Metrics},
+    models::{
+        activity::DailyQuota,
+        follow::{Follow, FollowStatus},
+        post::{normalize_topic, ContentWarning, Post, PostLifetime},
+        user::{ProfileColor, Role, User, WarningPreference},
+    },
+    storage::ImageStorage,
+};
 
-use self::token::{Claims, ENCODING_KEY};
+use self::{guards::AuthUser, token::Claims};
 
+pub mod admin;
+pub mod conversations;
+#[cfg(feature = "debug-routes")]
 pub mod debug;
+pub mod devices;
+pub mod guards;
+pub mod health;
+pub mod images;
+pub mod metrics;
+pub mod reactions;
+pub mod replies;
+pub mod reports;
+pub mod restrictions;
+pub mod search;
 pub mod token;
+pub mod wellbeing;
 
 /// A form to get a username and password.
 /// Used to register or login a user.
 #[derive(FromForm)]
-struct UserForm {
-    username: String,
-    password: String,
+pub struct UserForm {
+    pub username: String,
+    pub password: String,
 }
 
 /// Takes an old and new password, to change the password of a user.
@@ -31,76 +54,369 @@ struct ChangePasswordForm {
 
 /// Gets the data needed to create a new post.
 #[derive(FromForm)]
-struct CreatePostForm<'a> {
-    content: Option<String>,
-    image: Option<TempFile<'a>>,
+pub struct CreatePostForm<'a> {
+    pub content: Option<String>,
+    pub image: Option<TempFile<'a>>,
+    /// How long the post lives. Posts live forever if this is left out.
+    pub lifetime: Option<PostLifetime>,
+    /// Shown in front of the post, which is collapsed behind it.
+    pub content_warning: Option<ContentWarning>,
 }
 
-#[post("/auth/register", data = "<user>")]
-fn auth_register(db: &State<DatabaseHandler>, user: Form<UserForm>) -> Result<String, String> {
-    let username = &user.username;
-    let password = &user.password;
+/// How many posts are shown per page in feeds and post listings.
+pub const POSTS_PER_PAGE: i64 = 20;
+
+/// How many topics a user can narrow random discovery down to.
+pub const MAX_FOLLOWED_TOPICS: usize = 20;
+
+/// Takes the new content of a post that is being edited.
+#[derive(FromForm)]
+struct EditPostForm {
+    content: String,
+}
+
+/// Changes to a user's settings. Settings that are left out stay the same.
+#[derive(FromForm, Default)]
+pub struct SettingsForm {
+    pub username: Option<String>,
+    pub profile_color: Option<ProfileColor>,
+    pub prefers_darkmode: Option<bool>,
+    pub send_read_receipts: Option<bool>,
+    pub is_private: Option<bool>,
+    /// Hashtags separated by spaces or commas, e.g. "#baking, gardening".
+    pub topics: Option<String>,
+    /// How posts with each warning are shown, e.g. `content_warnings[grief]=hide`.
+    pub content_warnings: HashMap<ContentWarning, WarningPreference>,
+    /// How many minutes a day the user wants to spend in Bread. 0 removes the budget.
+    pub daily_minutes: Option<u16>,
+    /// Whether the user has quiet hours, from `quiet_hours_start` to `quiet_hours_end`.
+    pub quiet_hours: Option<bool>,
+    pub quiet_hours_start: Option<u8>,
+    pub quiet_hours_end: Option<u8>,
+    /// How many minutes the user's clock is ahead of UTC.
+    pub utc_offset_minutes: Option<i32>,
+}
+
+/// What anyone may see of a user.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct PublicUser {
+    pub id: String,
+    pub name: String,
+    pub profile_color: ProfileColor,
+}
+
+impl From<&User> for PublicUser {
+    fn from(user: &User) -> Self {
+        PublicUser {
+            id: user.id.map(|id| id.to_string()).unwrap_or_default(),
+            name: user.name.clone(),
+            profile_color: user.preferences.profile_color,
+        }
+    }
+}
+
+/// What the author gets back after creating a post.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct CreatedPost {
+    pub id: String,
+    /// Set if the post suggests that the author is in crisis.
+    pub crisis_response: Option<CrisisResponse>,
+}
+
+/// Parse an id from a route into an ObjectId.
+pub fn parse_object_id(id: &str) -> Result<ObjectId, String> {
+    ObjectId::parse_str(id).map_err(|_| "Invalid id!".to_string())
+}
+
+/// Get the id of a user that was read from the database.
+pub fn id_of(user: &User) -> Result<ObjectId, String> {
+    user.id.ok_or_else(|| "User has no id!".to_string())
+}
+
+/// Create and save a post, running its content through the classifier first.
+/// A post that triggers the classifier is still published, but the author is shown helplines.
+pub async fn publish_post(
+    db: &DatabaseHandler,
+    config: &BreadConfig,
+    classifier: &PostClassifier,
+    storage: &ImageStorage,
+    metrics: &Metrics,
+    author: &User,
+    post: &mut CreatePostForm<'_>,
+) -> Result<CreatedPost, String> {
+    let author_id = id_of(author)?;
+
+    let content = post
+        .content
+        .as_deref()
+        .map(str::trim)
+        .filter(|content| !content.is_empty())
+        .map(str::to_owned);
+
+    // Browsers send an empty file when no image was picked.
+    let image = post.image.as_mut().filter(|image| image.len() > 0);
+
+    if content.is_none() && image.is_none() {
+        return Err("A post can't be empty!".to_string());
+    }
+
+    if !wellbeing::take_quota(db, author, DailyQuota::Posts, config.posts_per_day)? {
+        return Err(format!(
+            "You can only create {} posts per day, try again tomorrow.",
+            config.posts_per_day
+        ));
+    }
+
+    let crisis_response = content
+        .as_deref()
+        .and_then(|content| classifier.check(content));
+
+    let image = match image {
+        Some(image) => Some(storage.save(image).await?),
+        None => None,
+    };
+
+    let mut new_post = Post::create(author_id, content, image);
+    new_post.expires_at = post
+        .lifetime
+        .unwrap_or_default()
+        .expires_at(new_post.created_at);
+    new_post.content_warning = post.content_warning;
+    new_post.author_private = author.preferences.is_private;
+    new_post.excluded_from_discovery = crisis_response
+        .as_ref()
+        .is_some_and(|response| response.exclude_from_discovery);
+
+    let id = db
+        .save_post(&new_post)?
+        .ok_or("Couldn't get ObjectId of the post!")?;
+    metrics.count(Event::PostCreated);
+
+    Ok(CreatedPost {
+        id: id.to_string(),
+        crisis_response,
+    })
+}
+
+/// Make a user follow another user.
+/// Following a private account only sends a follow request, which the account has to accept.
+pub fn follow_user(
+    db: &DatabaseHandler,
+    follower: &User,
+    user_id: &str,
+) -> Result<FollowStatus, String> {
+    let follower_id = id_of(follower)?;
+    let followee_id = parse_object_id(user_id)?;
+
+    if follower_id == followee_id {
+        return Err("You can't follow yourself!".to_string());
+    }
+
+    // Users who have blocked each other can't see each other, so they are told the user doesn't exist.
+    let followee = match db.find_user_by_id(&followee_id)? {
+        Some(followee) if !db.is_blocked_between(&follower_id, &followee_id)? => followee,
+        _ => return Err("No such user!".to_string()),
+    };
+
+    let status = if followee.preferences.is_private {
+        FollowStatus::Pending
+    } else {
+        FollowStatus::Accepted
+    };
+    db.save_follow(&Follow::create(follower_id, followee_id, status))?;
+
+    // Following someone twice keeps the first follow, which may already have been accepted.
+    Ok(db
+        .find_follow(&follower_id, &followee_id)?
+        .map_or(status, |follow| follow.status))
+}
 
-    let created_user = User::create(username.to_owned(), password.to_owned())?;
+/// Check if a viewer may see the posts of an author.
+/// Nobody can see the posts of someone they have blocked or been blocked by,
+/// and only the accepted followers of a private account can see its posts.
+pub fn can_see_posts_of(
+    db: &DatabaseHandler,
+    viewer: &ObjectId,
+    author: &ObjectId,
+) -> Result<bool, String> {
+    if viewer == author {
+        return Ok(true);
+    }
+
+    if db.is_blocked_between(viewer, author)? {
+        return Ok(false);
+    }
 
-    let user_in_db = db.find_user_by_name(username)?;
-    if user_in_db.is_some() {
-        return Err("User already exists with that name!".to_string());
+    match db.find_user_by_id(author)? {
+        Some(author_user) if author_user.preferences.is_private => db.is_following(viewer, author),
+        Some(_) => Ok(true),
+        None => Ok(false),
     }
+}
+
+/// Accept or decline a request to follow a user.
+pub fn answer_follow_request(
+    db: &DatabaseHandler,
+    user: &User,
+    follower_id: &str,
+    accept: bool,
+) -> Result<(), String> {
+    let user_id = id_of(user)?;
+    let follower_id = parse_object_id(follower_id)?;
 
-    let user_id = db.save_user(&created_user)?;
+    let found = if accept {
+        db.accept_follow_request(&follower_id, &user_id)?
+            .matched_count
+            > 0
+    } else {
+        db.decline_follow_request(&follower_id, &user_id)?
+            .deleted_count
+            > 0
+    };
 
-    match user_id {
-        Some(id) => Ok(id.to_string()),
-        None => Err("Couldn't get ObjectId of the user!".to_string()),
+    if !found {
+        return Err("No such follow request!".to_string());
     }
+
+    Ok(())
+}
+
+/// Get the users that have asked to follow a user, sorted by name.
+pub fn follow_requests(db: &DatabaseHandler, user: &User) -> Result<Vec<User>, String> {
+    db.find_users_by_ids(&db.find_follow_requests(&id_of(user)?)?)
+}
+
+/// Make a user stop following another user.
+pub fn unfollow_user(db: &DatabaseHandler, follower: &User, user_id: &str) -> Result<(), String> {
+    db.delete_follow(&id_of(follower)?, &parse_object_id(user_id)?)?;
+
+    Ok(())
+}
+
+/// Get a page of the posts written by the users that a user follows.
+/// Posts by users the user has muted, and posts with warnings the user always hides, are left out.
+/// So are hidden posts, unless the user is a moderator.
+pub fn friends_feed(
+    db: &DatabaseHandler,
+    user: &User,
+    cursor: Option<&str>,
+) -> Result<Page<Post>, String> {
+    let user_id = id_of(user)?;
+    let hidden = db.find_hidden_users(&user_id)?;
+    let followees: Vec<_> = db
+        .find_followees(&user_id)?
+        .into_iter()
+        .filter(|followee| !hidden.contains(followee))
+        .collect();
+
+    db.find_posts_by_authors(
+        &followees,
+        &user.preferences.hidden_warnings,
+        user.role >= Role::Moderator,
+        cursor,
+        POSTS_PER_PAGE,
+    )
+}
+
+/// Get a page of the posts written by a user, if the viewer may see them.
+/// Every listing of another user's posts goes through this, so that none can skip the visibility check.
+/// Posts with warnings the viewer always hides are left out, unless they are the viewer's own.
+/// Hidden posts are only listed to their author and moderators.
+pub fn posts_of_user(
+    db: &DatabaseHandler,
+    viewer: &User,
+    author_id: &str,
+    cursor: Option<&str>,
+) -> Result<Page<Post>, String> {
+    let viewer_id = id_of(viewer)?;
+    let author_id = parse_object_id(author_id)?;
+    if !can_see_posts_of(db, &viewer_id, &author_id)? {
+        return Err("You can't see this user's posts!".to_string());
+    }
+
+    let hidden_warnings = if viewer_id == author_id {
+        &[][..]
+    } else {
+        &viewer.preferences.hidden_warnings[..]
+    };
+    db.find_posts_by_authors(
+        &[author_id],
+        hidden_warnings,
+        viewer.sees_hidden_posts_of(&author_id),
+        cursor,
+        POSTS_PER_PAGE,
+    )
+}
+
+#[post("/auth/register", data = "<user>")]
+fn auth_register(
+    db: &State<DatabaseHandler>,
+    config: &State<BreadConfig>,
+    metrics: &State<Metrics>,
+    user: Form<UserForm>,
+) -> Result<String, String> {
+    let user_id = db.register_user(&user.username, &user.password, &config.argon2)?;
+    metrics.count(Event::Registration);
+
+    Ok(user_id.to_string())
 }
 
 #[post("/auth/login", data = "<user>")]
 fn auth_login(
     db: &State<DatabaseHandler>,
+    config: &State<BreadConfig>,
+    metrics: &State<Metrics>,
     cookies: &CookieJar<'_>,
     user: Form<UserForm>,
 ) -> Result<Json<User>, String> {
-    let username = &user.username;
-    let password = &user.password;
+    let user = log_in(db, config, metrics, cookies, &user.username, &user.password)?;
 
-    let user = db.login_user(username, password)?;
+    Ok(Json(user))
+}
 
-    let claim = Claims {
-        exp: 0,
-        sub: user.name,
+/// Check a user's credentials and, if they are correct, give the user a session cookie.
+/// This is shared by the API and the server-rendered login and register forms.
+pub fn log_in(
+    db: &DatabaseHandler,
+    config: &BreadConfig,
+    metrics: &Metrics,
+    cookies: &CookieJar<'_>,
+    username: &str,
+    password: &str,
+) -> Result<User, String> {
+    let user = match db.login_user(username, password) {
+        Ok(user) => user,
+        Err(err) => {
+            metrics.count(Event::LoginFailed);
+            return Err(err);
+        }
     };
+    metrics.count(Event::LoginSucceeded);
 
-    let token = jsonwebtoken::encode(&Header::default(), &claim, &ENCODING_KEY)
-        .map_err(|err| err.to_string())?;
+    cookies.add(Claims::new(&id_of(&user)?).into_cookie(&config.jwt_secret)?);
 
-    cookies.add(
-        Cookie::build("api-token", token)
-            .path("/api")
-            .secure(true)
-            .http_only(true)
-            .finish(),
-    );
-
-    Ok(Json(user))
+    Ok(user)
 }
 
 #[post("/auth/change-password", data = "<change_pass>")]
 fn auth_change_pass(
     db: &State<DatabaseHandler>,
+    config: &State<BreadConfig>,
+    user: AuthUser,
     change_pass: Form<ChangePasswordForm>,
 ) -> Result<(), String> {
     let old_password = &change_pass.old_password;
     let new_password = &change_pass.new_password;
-    let username = "Placeholder";
+    let username = &user.0.name;
 
     let is_old_password_is_correct = db.login_user(username, old_password).is_ok();
     if !is_old_password_is_correct {
         return Err("Old password is incorrect!".to_string());
     }
 
-    let hashed_password = User::hash_password(new_password)?;
+    let hashed_password = User::hash_password(new_password, &config.argon2)?;
 
     db.change_password(username, &hashed_password)?;
     //                                            ^--- This returns error if any, otherwise continues running.
@@ -109,16 +425,193 @@ fn auth_change_pass(
 }
 
 #[post("/create-post", data = "<post>")]
-fn create_post(db: &State<DatabaseHandler>, post: Form<CreatePostForm<'_>>) {}
+async fn create_post(
+    db: &State<DatabaseHandler>,
+    config: &State<BreadConfig>,
+    classifier: &State<PostClassifier>,
+    storage: &State<ImageStorage>,
+    metrics: &State<Metrics>,
+    user: AuthUser,
+    mut post: Form<CreatePostForm<'_>>,
+) -> Result<Json<CreatedPost>, String> {
+    publish_post(db, config, classifier, storage, metrics, &user.0, &mut post)
+        .await
+        .map(Json)
+}
+
+#[post("/edit-post/<post_id>", data = "<post>")]
+fn edit_post(
+    db: &State<DatabaseHandler>,
+    classifier: &State<PostClassifier>,
+    user: AuthUser,
+    post_id: &str,
+    post: Form<EditPostForm>,
+) -> Result<Json<Option<CrisisResponse>>, String> {
+    let content = post.content.trim();
+    if content.is_empty() {
+        return Err("A post can't be empty!".to_string());
+    }
+
+    let crisis_response = classifier.check(content);
+    let exclude_from_discovery = crisis_response
+        .as_ref()
+        .is_some_and(|response| response.exclude_from_discovery);
+
+    let result = db.edit_post(
+        &id_of(&user.0)?,
+        &parse_object_id(post_id)?,
+        content,
+        exclude_from_discovery,
+    )?;
+
+    if result.matched_count == 0 {
+        return Err("Post not found!".to_string());
+    }
+
+    Ok(Json(crisis_response))
+}
+
+#[get("/posts/<user_id>?<cursor>")]
+fn posts_by_user(
+    db: &State<DatabaseHandler>,
+    user: AuthUser,
+    user_id: &str,
+    cursor: Option<&str>,
+) -> Result<Json<Page<Post>>, String> {
+    posts_of_user(db, &user.0, user_id, cursor).map(Json)
+}
+
+#[get("/feed?<cursor>")]
+fn feed(
+    db: &State<DatabaseHandler>,
+    user: AuthUser,
+    cursor: Option<&str>,
+) -> Result<Json<Page<Post>>, String> {
+    friends_feed(db, &user.0, cursor).map(Json)
+}
+
+/// Parse the topics a user picked, e.g. "#baking, gardening", into normalized topics without duplicates.
+pub fn parse_topic_list(topics: &str) -> Result<Vec<String>, String> {
+    let mut parsed: Vec<String> = vec![];
+
+    for hashtag in topics
+        .split([' ', ','])
+        .filter(|hashtag| !hashtag.is_empty())
+    {
+        let topic =
+            normalize_topic(hashtag).ok_or_else(|| format!("\"{}\" can't be a topic!", hashtag))?;
+        if !parsed.contains(&topic) {
+            parsed.push(topic);
+        }
+    }
+
+    if parsed.len() > MAX_FOLLOWED_TOPICS {
+        return Err(format!(
+            "You can pick at most {} topics!",
+            MAX_FOLLOWED_TOPICS
+        ));
+    }
+
+    Ok(parsed)
+}
+
+/// Change a user's settings.
+pub fn change_settings(
+    db: &DatabaseHandler,
+    user: &User,
+    settings: &SettingsForm,
+) -> Result<(), String> {
+    let name = match &settings.username {
+        Some(name) => name.trim(),
+        None => &user.name,
+    };
+
+    let mut preferences = user.preferences.clone();
+    if let Some(profile_color) = settings.profile_color {
+        preferences.profile_color = profile_color;
+    }
+    if let Some(prefers_darkmode) = settings.prefers_darkmode {
+        preferences.prefers_darkmode = prefers_darkmode;
+    }
+    if let Some(send_read_receipts) = settings.send_read_receipts {
+        preferences.send_read_receipts = send_read_receipts;
+    }
+    if let Some(is_private) = settings.is_private {
+        preferences.is_private = is_private;
+    }
+    if let Some(topics) = &settings.topics {
+        preferences.topics = parse_topic_list(topics)?;
+    }
+    for (warning, preference) in &settings.content_warnings {
+        preferences.set_warning_preference(*warning, *preference);
+    }
+    wellbeing::apply_wellbeing_settings(&mut preferences.wellbeing, settings)?;
+
+    let user_id = id_of(user)?;
+    db.update_user_settings(&user_id, name, &preferences)?;
+
+    if user.preferences.is_private != preferences.is_private {
+        db.set_posts_author_private(&user_id, preferences.is_private)?;
+    }
+
+    // A public account has no use for follow requests.
+    if user.preferences.is_private && !preferences.is_private {
+        db.accept_all_follow_requests(&user_id)?;
+    }
 
-#[post("/settings")]
-fn settings(db: &State<DatabaseHandler>) {}
+    Ok(())
+}
+
+#[post("/settings", data = "<settings>")]
+fn settings(
+    db: &State<DatabaseHandler>,
+    user: AuthUser,
+    settings: Form<SettingsForm>,
+) -> Result<(), String> {
+    change_settings(db, &user.0, &settings)
+}
 
 #[post("/follow/<user_id>")]
-fn follow(db: &State<DatabaseHandler>, user_id: &str) {}
+fn follow(
+    db: &State<DatabaseHandler>,
+    user: AuthUser,
+    user_id: &str,
+) -> Result<Json<FollowStatus>, String> {
+    follow_user(db, &user.0, user_id).map(Json)
+}
 
 #[post("/unfollow/<user_id>")]
-fn unfollow(db: &State<DatabaseHandler>, user_id: &str) {}
+fn unfollow(db: &State<DatabaseHandler>, user: AuthUser, user_id: &str) -> Result<(), String> {
+    unfollow_user(db, &user.0, user_id)
+}
+
+#[get("/follow-requests")]
+fn requests(db: &State<DatabaseHandler>, user: AuthUser) -> Result<Json<Vec<PublicUser>>, String> {
+    Ok(Json(
+        follow_requests(db, &user.0)?
+            .iter()
+            .map(PublicUser::from)
+            .collect(),
+    ))
+}
+
+#[post("/follow-requests/<user_id>/accept")]
+fn accept_request(
+    db: &State<DatabaseHandler>,
+    user: AuthUser,
+    user_id: &str,
+) -> Result<(), String> {
+    answer_follow_request(db, &user.0, user_id, true)
+}
+
+#[post("/follow-requests/<user_id>/decline")]
+fn decline_request(
+    db: &State<DatabaseHandler>,
+    user: AuthUser,
+    user_id: &str,
+) -> Result<(), String> {
+    answer_follow_request(db, &user.0, user_id, false)
+}
 
 pub fn get_api_routes() -> Vec<Route> {
     routes![
@@ -126,8 +619,140 @@ pub fn get_api_routes() -> Vec<Route> {
         auth_login,
         auth_change_pass,
         create_post,
+        edit_post,
+        posts_by_user,
+        feed,
         settings,
         follow,
-        unfollow
+        unfollow,
+        requests,
+        accept_request,
+        decline_request
     ]
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::models::{report::ReportReason, restriction::RestrictionKind};
+
+    use super::*;
+
+    /// Needs a database, like the tests in `database`.
+    #[test]
+    fn follow_requests_decide_who_sees_private_posts() {
+        let config = BreadConfig::default();
+        let db = DatabaseHandler::create_connection(&config.database_name)
+            .expect("Could not connect to the database!");
+        let register = |role: &str| {
+            let name = format!("{}-{}", role, ObjectId::new());
+            let id = db.register_user(&name, "password", &config.argon2).unwrap();
+            db.find_user_by_id(&id).unwrap().unwrap()
+        };
+        let (author, accepted, declined) = (
+            register("author"),
+            register("accepted"),
+            register("declined"),
+        );
+        let (author_id, accepted_id, declined_id) = (
+            id_of(&author).unwrap(),
+            id_of(&accepted).unwrap(),
+            id_of(&declined).unwrap(),
+        );
+
+        // Public accounts can be seen and followed right away.
+        assert!(can_see_posts_of(&db, &accepted_id, &author_id).unwrap());
+
+        let private = SettingsForm {
+            is_private: Some(true),
+            ..Default::default()
+        };
+        change_settings(&db, &author, &private).unwrap();
+        let author = db.find_user_by_id(&author_id).unwrap().unwrap();
+        assert!(can_see_posts_of(&db, &author_id, &author_id).unwrap());
+        assert!(!can_see_posts_of(&db, &accepted_id, &author_id).unwrap());
+
+        for follower in [&accepted, &declined] {
+            assert_eq!(
+                follow_user(&db, follower, &author_id.to_hex()).unwrap(),
+                FollowStatus::Pending
+            );
+        }
+        assert_eq!(follow_requests(&db, &author).unwrap().len(), 2);
+
+        answer_follow_request(&db, &author, &accepted_id.to_hex(), true).unwrap();
+        answer_follow_request(&db, &author, &declined_id.to_hex(), false).unwrap();
+        assert!(follow_requests(&db, &author).unwrap().is_empty());
+        assert!(can_see_posts_of(&db, &accepted_id, &author_id).unwrap());
+        assert!(!can_see_posts_of(&db, &declined_id, &author_id).unwrap());
+
+        // Requests can only be answered once.
+        assert!(answer_follow_request(&db, &author, &declined_id.to_hex(), true).is_err());
+
+        // Blocking hides the posts even from accepted followers.
+        restrictions::restrict_user(&db, &author, &accepted_id.to_hex(), RestrictionKind::Block)
+            .unwrap();
+        assert!(!can_see_posts_of(&db, &accepted_id, &author_id).unwrap());
+
+        for id in [author_id, accepted_id, declined_id] {
+            db.delete_account(&id).unwrap();
+        }
+    }
+
+    /// Needs a database, like the tests in `database`.
+    #[test]
+    fn reports_from_distinct_users_hide_posts() {
+        let config = BreadConfig::default();
+        let db = DatabaseHandler::create_connection(&config.database_name)
+            .expect("Could not connect to the database!");
+        let register = |role: &str| {
+            let name = format!("{}-{}", role, ObjectId::new());
+            let id = db.register_user(&name, "password", &config.argon2).unwrap();
+            db.find_user_by_id(&id).unwrap().unwrap()
+        };
+        let author = register("author");
+        let author_id = id_of(&author).unwrap();
+        let reporters: Vec<User> = (0..reports::REPORTS_TO_HIDE_POST)
+            .map(|_| register("reporter"))
+            .collect();
+        let post_id = db
+            .save_post(&Post::create(author_id, Some("Crumbs".to_string()), None))
+            .unwrap()
+            .unwrap();
+        let report = |reporter: &User| {
+            reports::report_post(&db, reporter, &post_id.to_hex(), ReportReason::Spam)
+        };
+        let is_hidden = || db.find_post_by_id(&post_id).unwrap().unwrap().hidden;
+
+        assert!(report(&author).is_err());
+
+        // Reporting again before the report is resolved isn't counted.
+        report(&reporters[0]).unwrap();
+        report(&reporters[0]).unwrap();
+        assert_eq!(db.count_open_reports(&post_id).unwrap(), 1);
+
+        let (last, others) = reporters[1..].split_last().unwrap();
+        for reporter in others {
+            report(reporter).unwrap();
+        }
+        assert!(!is_hidden());
+
+        report(last).unwrap();
+        assert!(is_hidden());
+        let queued = reports::report_queue(&db)
+            .unwrap()
+            .into_iter()
+            .find(|reported| reported.post_id == post_id.to_hex())
+            .unwrap();
+        assert_eq!(queued.report_count as u64, reports::REPORTS_TO_HIDE_POST);
+
+        // A hidden post can't be found, and so can't be reported, by those who didn't see it before.
+        let stranger = register("stranger");
+        assert_eq!(report(&stranger).unwrap_err(), "Post not found!");
+
+        let mut ids = vec![author_id, id_of(&stranger).unwrap()];
+        ids.extend(reporters.iter().map(|reporter| id_of(reporter).unwrap()));
+        for id in ids {
+            db.delete_account(&id).unwrap();
+        }
+    }
+}
@@ -0,0 +1,128 @@
+use rocket::{serde::json::Json, Route, State};
+use serde::Serialize;
+
+use crate::{
+    database::DatabaseHandler,
+    models::{
+        reaction::{Reaction, ReactionKind},
+        user::User,
+    },
+};
+
+use super::{can_see_posts_of, guards::AuthUser, id_of, parse_object_id};
+
+/// A reaction as shown to the author of the post.
+/// There are deliberately no counts, only who sent what.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct ReactionView {
+    pub post_id: String,
+    pub from: String,
+    pub kind: ReactionKind,
+}
+
+/// React to a post, replacing the user's earlier reaction to it.
+pub fn react_to_post(
+    db: &DatabaseHandler,
+    reactor: &User,
+    post_id: &str,
+    kind: ReactionKind,
+) -> Result<(), String> {
+    let reactor_id = id_of(reactor)?;
+    let post_id = parse_object_id(post_id)?;
+
+    let post = match db.find_post_by_id(&post_id)? {
+        Some(post) => post,
+        None => return Err("Post not found!".to_string()),
+    };
+
+    if post.author == reactor_id {
+        return Err("You can't react to your own post!".to_string());
+    }
+
+    if !can_see_posts_of(db, &reactor_id, &post.author)? {
+        return Err("Post not found!".to_string());
+    }
+
+    db.save_reaction(&Reaction::create(post_id, reactor_id, kind))
+}
+
+/// Take back a user's reaction to a post. Doing so when there is no reaction does nothing.
+pub fn remove_reaction(db: &DatabaseHandler, reactor: &User, post_id: &str) -> Result<(), String> {
+    db.delete_reaction(&parse_object_id(post_id)?, &id_of(reactor)?)?;
+    Ok(())
+}
+
+/// Get the reactions to some of the author's posts, newest first.
+/// Reactions to posts written by someone else are left out.
+pub fn received_reactions(
+    db: &DatabaseHandler,
+    author: &User,
+    post_ids: &[&str],
+) -> Result<Vec<ReactionView>, String> {
+    let author_id = id_of(author)?;
+    let mut own_posts = vec![];
+    for post_id in post_ids {
+        let post_id = parse_object_id(post_id)?;
+        if db
+            .find_post_by_id(&post_id)?
+            .is_some_and(|post| post.author == author_id)
+        {
+            own_posts.push(post_id);
+        }
+    }
+
+    let reactions = db.find_reactions_to_posts(&own_posts)?;
+
+    let reactor_ids: Vec<_> = reactions.iter().map(|reaction| reaction.reactor).collect();
+    let reactors = db.find_users_by_ids(&reactor_ids)?;
+
+    Ok(reactions
+        .into_iter()
+        .filter_map(|reaction| {
+            // Reactions from deleted users are left out.
+            let reactor = reactors
+                .iter()
+                .find(|user| user.id == Some(reaction.reactor))?;
+
+            Some(ReactionView {
+                post_id: reaction.post.to_string(),
+                from: reactor.name.clone(),
+                kind: reaction.kind,
+            })
+        })
+        .collect())
+}
+
+#[put("/react/<post_id>/<kind>")]
+fn react(
+    db: &State<DatabaseHandler>,
+    user: AuthUser,
+    post_id: &str,
+    kind: ReactionKind,
+) -> Result<(), String> {
+    react_to_post(db, &user.0, post_id, kind)
+}
+
+#[delete("/react/<post_id>")]
+fn unreact(db: &State<DatabaseHandler>, user: AuthUser, post_id: &str) -> Result<(), String> {
+    remove_reaction(db, &user.0, post_id)
+}
+
+#[get("/reactions/<post_id>")]
+fn reactions(
+    db: &State<DatabaseHandler>,
+    user: AuthUser,
+    post_id: &str,
+) -> Result<Json<Vec<ReactionView>>, String> {
+    let post = db.find_post_by_id(&parse_object_id(post_id)?)?;
+    if post.is_none_or(|post| Some(post.author) != user.0.id) {
+        return Err("Only the author of a post can see its reactions!".to_string());
+    }
+
+    received_reactions(db, &user.0, &[post_id]).map(Json)
+}
+
+pub fn get_reaction_routes() -> Vec<Route> {
+    routes![react, unreact, reactions]
+}
@@ -0,0 +1,183 @@
+use mongodb::bson::oid::ObjectId;
+use rocket::{form::Form, serde::json::Json, Route, State};
+
+use crate::{
+    database::{pagination::Page, DatabaseHandler},
+    models::{post::Post, reply::Reply, user::User},
+};
+
+use super::{can_see_posts_of, guards::AuthUser, id_of, parse_object_id};
+
+/// How many replies a user can send per hour.
+pub const REPLIES_PER_HOUR: u32 = 10;
+
+/// How many replies are shown per page in the inbox.
+pub const REPLIES_PER_PAGE: i64 = 20;
+
+/// The content of a reply.
+#[derive(FromForm)]
+pub struct ReplyForm {
+    pub content: String,
+}
+
+/// Check that a user may reply to a post, given whether they may see the author's posts at all.
+/// Posts that can't be seen are answered as if they didn't exist.
+fn check_reply_target(
+    post: Option<Post>,
+    sender_id: &ObjectId,
+    sees_author: bool,
+) -> Result<Post, String> {
+    let post = match post {
+        Some(post) if !post.is_expired() && sees_author => post,
+        _ => return Err("Post not found!".to_string()),
+    };
+
+    if &post.author == sender_id {
+        return Err("You can't reply to your own post!".to_string());
+    }
+
+    Ok(post)
+}
+
+/// Send a private reply to the author of a post.
+pub fn send_reply(
+    db: &DatabaseHandler,
+    sender: &User,
+    post_id: &str,
+    content: &str,
+) -> Result<(), String> {
+    let sender_id = id_of(sender)?;
+    let post_id = parse_object_id(post_id)?;
+
+    let content = content.trim();
+    if content.is_empty() {
+        return Err("A reply can't be empty!".to_string());
+    }
+
+    let post = db.find_post_by_id(&post_id)?;
+    let sees_author = match &post {
+        Some(post) => can_see_posts_of(db, &sender_id, &post.author)?,
+        None => false,
+    };
+    let post = check_reply_target(post, &sender_id, sees_author)?;
+
+    // The reply is counted before it is saved, so that one past the limit is never stored.
+    if !db.take_reply_slot(&sender_id, REPLIES_PER_HOUR)? {
+        return Err("You have sent too many replies, try again in a while.".to_string());
+    }
+
+    db.save_reply(&Reply::create(
+        post_id,
+        sender_id,
+        post.author,
+        content.to_string(),
+    ))?;
+
+    Ok(())
+}
+
+/// Mark one of the user's replies as read.
+pub fn mark_read(db: &DatabaseHandler, user: &User, reply_id: &str) -> Result<(), String> {
+    let result = db.mark_reply_read(&id_of(user)?, &parse_object_id(reply_id)?)?;
+
+    if result.matched_count == 0 {
+        return Err("Reply not found!".to_string());
+    }
+
+    Ok(())
+}
+
+#[post("/reply/<post_id>", data = "<reply>")]
+fn reply(
+    db: &State<DatabaseHandler>,
+    user: AuthUser,
+    post_id: &str,
+    reply: Form<ReplyForm>,
+) -> Result<(), String> {
+    send_reply(db, &user.0, post_id, &reply.content)
+}
+
+#[get("/replies?<cursor>")]
+fn replies(
+    db: &State<DatabaseHandler>,
+    user: AuthUser,
+    cursor: Option<&str>,
+) -> Result<Json<Page<Reply>>, String> {
+    db.find_replies_to(&id_of(&user.0)?, cursor, REPLIES_PER_PAGE)
+        .map(Json)
+}
+
+#[post("/replies/<reply_id>/read")]
+fn read(db: &State<DatabaseHandler>, user: AuthUser, reply_id: &str) -> Result<(), String> {
+    mark_read(db, &user.0, reply_id)
+}
+
+pub fn get_reply_routes() -> Vec<Route> {
+    routes![reply, replies, read]
+}
+
+#[cfg(test)]
+mod tests {
+    use mongodb::bson::DateTime;
+
+    use crate::config::BreadConfig;
+
+    use super::*;
+
+    /// Needs a database, like the tests in `database`.
+    #[test]
+    fn replies_past_the_hourly_limit_are_not_stored() {
+        let config = BreadConfig::default();
+        let db = DatabaseHandler::create_connection(&config.database_name)
+            .expect("Could not connect to the database!");
+        let register = |role: &str| {
+            let name = format!("{}-{}", role, ObjectId::new());
+            let id = db.register_user(&name, "password", &config.argon2).unwrap();
+            db.find_user_by_id(&id).unwrap().unwrap()
+        };
+        let (author, sender) = (register("author"), register("sender"));
+        let author_id = id_of(&author).unwrap();
+        let post_id = db
+            .save_post(&Post::create(author_id, Some("Hello".to_string()), None))
+            .unwrap()
+            .unwrap()
+            .to_hex();
+
+        for i in 0..REPLIES_PER_HOUR {
+            send_reply(&db, &sender, &post_id, &format!("Reply {}", i)).unwrap();
+        }
+        assert!(send_reply(&db, &sender, &post_id, "One too many").is_err());
+
+        let inbox = db
+            .find_replies_to(&author_id, None, i64::from(REPLIES_PER_HOUR) + 1)
+            .unwrap()
+            .items;
+        assert_eq!(inbox.len(), REPLIES_PER_HOUR as usize);
+        assert!(inbox.iter().all(|reply| reply.content != "One too many"));
+
+        for user in [author, sender] {
+            db.delete_account(&id_of(&user).unwrap()).unwrap();
+        }
+    }
+
+    #[test]
+    fn replies_follow_post_visibility() {
+        let (author, sender) = (ObjectId::new(), ObjectId::new());
+        let post = Post::create(author, Some("Hello".to_string()), None);
+
+        assert_eq!(
+            check_reply_target(Some(post.clone()), &sender, true)
+                .unwrap()
+                .author,
+            author
+        );
+        assert!(check_reply_target(None, &sender, true).is_err());
+        // E.g. a private author the sender doesn't follow, or one who has blocked them.
+        assert!(check_reply_target(Some(post.clone()), &sender, false).is_err());
+        assert!(check_reply_target(Some(post.clone()), &author, true).is_err());
+
+        let mut expired = post;
+        expired.expires_at = Some(DateTime::from_millis(0));
+        assert!(check_reply_target(Some(expired), &sender, true).is_err());
+    }
+}
@@ -0,0 +1,191 @@
+use mongodb::bson::oid::ObjectId;
+use rocket::{form::Form, Route, State};
+use serde::Serialize;
+
+use crate::{
+    database::DatabaseHandler,
+    models::{
+        audit::{AuditEntry, ModerationAction},
+        report::{Report, ReportReason},
+        user::User,
+    },
+    storage::ImageStorage,
+};
+
+use super::{admin, can_see_posts_of, guards::AuthUser, id_of, parse_object_id};
+
+/// A post is hidden from random discovery once it has this many unresolved reports.
+pub const REPORTS_TO_HIDE_POST: u64 = 3;
+
+/// The reason a user gives when reporting a post.
+#[derive(FromForm)]
+struct ReportForm {
+    reason: ReportReason,
+}
+
+/// A reported post together with a summary of its unresolved reports, as shown to moderators.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct ReportedPost {
+    pub post_id: String,
+    pub content: Option<String>,
+    pub hidden: bool,
+    /// The author has deleted the post since it was reported. Its reports only need to be dismissed.
+    pub deleted: bool,
+    pub report_count: usize,
+    pub reasons: Vec<ReportReason>,
+}
+
+/// Report a post. Reporting the same post twice does nothing.
+/// Once a post has enough reports it is hidden until a moderator resolves the reports.
+/// Posts the reporter can't see are answered as if they didn't exist, so that reports can't reveal them.
+pub fn report_post(
+    db: &DatabaseHandler,
+    reporter: &User,
+    post_id: &str,
+    reason: ReportReason,
+) -> Result<(), String> {
+    let reporter_id = id_of(reporter)?;
+    let post_id = parse_object_id(post_id)?;
+
+    let post = match db.find_post_by_id(&post_id)? {
+        Some(post)
+            if !post.is_expired()
+                && (!post.hidden || reporter.sees_hidden_posts_of(&post.author))
+                && can_see_posts_of(db, &reporter_id, &post.author)? =>
+        {
+            post
+        }
+        _ => return Err("Post not found!".to_string()),
+    };
+
+    if post.author == reporter_id {
+        return Err("You can't report your own post!".to_string());
+    }
+
+    let is_new_report = db.save_report(&Report::create(post_id, reporter_id, reason))?;
+
+    if is_new_report && !post.hidden && db.count_open_reports(&post_id)? >= REPORTS_TO_HIDE_POST {
+        db.set_post_hidden(&post_id, true)?;
+    }
+
+    Ok(())
+}
+
+/// Gather the reasons of reports by the post they report, keeping the order in which the posts were first reported.
+fn group_by_post(reports: Vec<Report>) -> Vec<(ObjectId, Vec<ReportReason>)> {
+    let mut grouped: Vec<(ObjectId, Vec<ReportReason>)> = vec![];
+
+    for report in reports {
+        match grouped.iter_mut().find(|(post, _)| post == &report.post) {
+            Some((_, reasons)) => reasons.push(report.reason),
+            None => grouped.push((report.post, vec![report.reason])),
+        }
+    }
+
+    grouped
+}
+
+/// Get all posts with unresolved reports, in the order they were first reported.
+/// This only reads the reports, even those of posts that have been deleted since.
+pub fn report_queue(db: &DatabaseHandler) -> Result<Vec<ReportedPost>, String> {
+    let mut reported_posts = vec![];
+    for (post_id, mut reasons) in group_by_post(db.find_open_reports()?) {
+        // The post may have been deleted by its author since it was reported.
+        let post = db.find_post_by_id(&post_id)?;
+
+        let report_count = reasons.len();
+        reasons.sort_by_key(|reason| *reason as u8);
+        reasons.dedup();
+
+        reported_posts.push(ReportedPost {
+            post_id: post_id.to_string(),
+            deleted: post.is_none(),
+            hidden: post.as_ref().is_some_and(|post| post.hidden),
+            content: post.and_then(|post| post.content),
+            report_count,
+            reasons,
+        });
+    }
+
+    Ok(reported_posts)
+}
+
+/// Decide that a reported post is fine. The post is shown again and its reports are resolved.
+/// This is also how the reports of a post that its author has deleted are cleared from the queue.
+pub fn dismiss_reports(
+    db: &DatabaseHandler,
+    moderator: &User,
+    post_id: &str,
+) -> Result<(), String> {
+    let moderator_id = id_of(moderator)?;
+    let post_id = parse_object_id(post_id)?;
+
+    if db.count_open_reports(&post_id)? == 0 {
+        return Err("No open reports found for this post!".to_string());
+    }
+
+    if db.find_post_by_id(&post_id)?.is_some() {
+        db.set_post_hidden(&post_id, false)?;
+    }
+    db.resolve_reports(&post_id)?;
+    db.save_audit_entry(&AuditEntry::create(
+        moderator_id,
+        ModerationAction::DismissReports,
+        post_id,
+    ))?;
+
+    Ok(())
+}
+
+/// Decide that a reported post breaks the rules. The post is removed and its reports are resolved.
+pub fn remove_reported_post(
+    db: &DatabaseHandler,
+    storage: &ImageStorage,
+    moderator: &User,
+    post_id: &str,
+) -> Result<(), String> {
+    admin::remove_post(db, storage, moderator, post_id)?;
+    db.resolve_reports(&parse_object_id(post_id)?)?;
+
+    Ok(())
+}
+
+#[post("/report/<post_id>", data = "<report>")]
+fn report(
+    db: &State<DatabaseHandler>,
+    user: AuthUser,
+    post_id: &str,
+    report: Form<ReportForm>,
+) -> Result<(), String> {
+    report_post(db, &user.0, post_id, report.reason)
+}
+
+pub fn get_report_routes() -> Vec<Route> {
+    routes![report]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_are_grouped_by_post() {
+        let (first, second) = (ObjectId::new(), ObjectId::new());
+        let report = |post, reason| Report::create(post, ObjectId::new(), reason);
+
+        let grouped = group_by_post(vec![
+            report(first, ReportReason::Spam),
+            report(second, ReportReason::SelfHarm),
+            report(first, ReportReason::Harassment),
+        ]);
+
+        assert_eq!(
+            grouped,
+            vec![
+                (first, vec![ReportReason::Spam, ReportReason::Harassment]),
+                (second, vec![ReportReason::SelfHarm]),
+            ]
+        );
+    }
+}
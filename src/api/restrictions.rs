@@ -0,0 +1,107 @@
+use rocket::{serde::json::Json, Route, State};
+use serde::Serialize;
+
+use crate::{
+    database::DatabaseHandler,
+    models::{
+        restriction::{Restriction, RestrictionKind},
+        user::User,
+    },
+};
+
+use super::{guards::AuthUser, id_of, parse_object_id, PublicUser};
+
+/// The users that a user has blocked or muted.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct Restrictions {
+    pub blocked: Vec<PublicUser>,
+    pub muted: Vec<PublicUser>,
+}
+
+/// Block or mute a user.
+/// Blocking also makes both users stop following each other.
+pub fn restrict_user(
+    db: &DatabaseHandler,
+    user: &User,
+    target_id: &str,
+    kind: RestrictionKind,
+) -> Result<(), String> {
+    let user_id = id_of(user)?;
+    let target_id = parse_object_id(target_id)?;
+
+    if user_id == target_id {
+        return Err("You can't do that to yourself!".to_string());
+    }
+
+    if db.find_user_by_id(&target_id)?.is_none() {
+        return Err("No such user!".to_string());
+    }
+
+    db.save_restriction(&Restriction::create(user_id, target_id, kind))?;
+
+    if kind == RestrictionKind::Block {
+        db.delete_follow(&user_id, &target_id)?;
+        db.delete_follow(&target_id, &user_id)?;
+    }
+
+    Ok(())
+}
+
+/// Unblock or unmute a user. Follows that were removed by a block aren't brought back.
+pub fn unrestrict_user(
+    db: &DatabaseHandler,
+    user: &User,
+    target_id: &str,
+    kind: RestrictionKind,
+) -> Result<(), String> {
+    db.delete_restriction(&id_of(user)?, &parse_object_id(target_id)?, kind)?;
+
+    Ok(())
+}
+
+/// Get the users that a user has blocked or muted, sorted by name.
+pub fn find_restrictions(db: &DatabaseHandler, user: &User) -> Result<Restrictions, String> {
+    let user_id = id_of(user)?;
+    let users_of = |kind| -> Result<Vec<PublicUser>, String> {
+        Ok(db
+            .find_users_by_ids(&db.find_restricted_users(&user_id, kind)?)?
+            .iter()
+            .map(PublicUser::from)
+            .collect())
+    };
+
+    Ok(Restrictions {
+        blocked: users_of(RestrictionKind::Block)?,
+        muted: users_of(RestrictionKind::Mute)?,
+    })
+}
+
+#[post("/block/<user_id>")]
+fn block(db: &State<DatabaseHandler>, user: AuthUser, user_id: &str) -> Result<(), String> {
+    restrict_user(db, &user.0, user_id, RestrictionKind::Block)
+}
+
+#[post("/unblock/<user_id>")]
+fn unblock(db: &State<DatabaseHandler>, user: AuthUser, user_id: &str) -> Result<(), String> {
+    unrestrict_user(db, &user.0, user_id, RestrictionKind::Block)
+}
+
+#[post("/mute/<user_id>")]
+fn mute(db: &State<DatabaseHandler>, user: AuthUser, user_id: &str) -> Result<(), String> {
+    restrict_user(db, &user.0, user_id, RestrictionKind::Mute)
+}
+
+#[post("/unmute/<user_id>")]
+fn unmute(db: &State<DatabaseHandler>, user: AuthUser, user_id: &str) -> Result<(), String> {
+    unrestrict_user(db, &user.0, user_id, RestrictionKind::Mute)
+}
+
+#[get("/restrictions")]
+fn restrictions(db: &State<DatabaseHandler>, user: AuthUser) -> Result<Json<Restrictions>, String> {
+    find_restrictions(db, &user.0).map(Json)
+}
+
+pub fn get_restriction_routes() -> Vec<Route> {
+    routes![block, unblock, mute, unmute, restrictions]
+}
@@ -0,0 +1,203 @@
+use std::time::Duration;
+
+use mongodb::bson::DateTime;
+use rocket::{serde::json::Json, Route, State};
+
+use crate::{
+    database::{pagination::Page, search::PostFilters, DatabaseHandler},
+    models::{post::Post, user::User},
+    rate_limit::RateLimiter,
+};
+
+use super::{guards::AuthUser, id_of, parse_object_id, PublicUser, POSTS_PER_PAGE};
+
+/// How many searches a user can make per minute, so that the list of users can't be scraped.
+pub const SEARCHES_PER_MINUTE: usize = 20;
+
+/// How many users a search for users finds at most. There is no next page, for the same reason.
+pub const USERS_PER_SEARCH: i64 = 20;
+
+/// Searches have to be at least this long, so that one letter can't list everyone.
+const MIN_QUERY_LENGTH: usize = 2;
+
+/// No username is longer than this, so longer queries can't find anyone.
+const MAX_QUERY_LENGTH: usize = 64;
+
+/// Searches for posts longer than this are cut off, since the words at the end hardly change the results.
+const MAX_POST_QUERY_LENGTH: usize = 200;
+
+/// A search for posts. `since` and `until` are dates like "2024-05-31", and both days are included.
+#[derive(FromForm)]
+pub struct PostSearch {
+    pub q: String,
+    pub author: Option<String>,
+    pub since: Option<String>,
+    pub until: Option<String>,
+    pub cursor: Option<String>,
+}
+
+/// Keeps track of how often each user searches.
+pub struct SearchLimiter(RateLimiter);
+
+impl Default for SearchLimiter {
+    fn default() -> Self {
+        SearchLimiter(RateLimiter::new(
+            SEARCHES_PER_MINUTE,
+            Duration::from_secs(60),
+        ))
+    }
+}
+
+/// Search for users by name. See `DatabaseHandler::search_users` for who can be found.
+pub fn search_users(
+    db: &DatabaseHandler,
+    limiter: &SearchLimiter,
+    user: &User,
+    query: &str,
+) -> Result<Vec<PublicUser>, String> {
+    let user_id = id_of(user)?;
+
+    let query = query.trim();
+    let length = query.chars().count();
+    if length < MIN_QUERY_LENGTH {
+        return Err(format!(
+            "Searches must be at least {} characters long!",
+            MIN_QUERY_LENGTH
+        ));
+    }
+    if length > MAX_QUERY_LENGTH {
+        return Err("That search is too long!".to_string());
+    }
+
+    if !limiter.0.try_attempt(&user_id) {
+        return Err("You are searching too often, try again in a minute.".to_string());
+    }
+
+    Ok(db
+        .search_users(&user_id, query, USERS_PER_SEARCH)?
+        .iter()
+        .map(PublicUser::from)
+        .collect())
+}
+
+/// Get an optional field of a search. Fields that were left empty in a form count as not given.
+fn given(field: &Option<String>) -> Option<&str> {
+    field
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+/// Parse a date like "2024-05-31" into the time that day starts, in UTC.
+fn parse_date(date: &str) -> Result<DateTime, String> {
+    DateTime::parse_rfc3339_str(format!("{}T00:00:00Z", date))
+        .map_err(|_| format!("\"{}\" isn't a date like 2024-05-31!", date))
+}
+
+/// Parse the last day a search covers into the time the next day starts, so that posts from all of that day are found.
+fn parse_until(date: &str) -> Result<DateTime, String> {
+    parse_date(date).map(|day| day.saturating_add_millis(24 * 60 * 60 * 1000))
+}
+
+/// Search for posts by their content. See `DatabaseHandler::search_posts` for which posts can be found.
+/// Each page counts as a search.
+pub fn search_posts(
+    db: &DatabaseHandler,
+    limiter: &SearchLimiter,
+    user: &User,
+    search: &PostSearch,
+) -> Result<Page<Post>, String> {
+    let user_id = id_of(user)?;
+
+    let query: String = search
+        .q
+        .trim()
+        .chars()
+        .take(MAX_POST_QUERY_LENGTH)
+        .collect();
+    if query.chars().count() < MIN_QUERY_LENGTH {
+        return Err(format!(
+            "Searches must be at least {} characters long!",
+            MIN_QUERY_LENGTH
+        ));
+    }
+
+    let filters = PostFilters {
+        author: given(&search.author).map(parse_object_id).transpose()?,
+        since: given(&search.since).map(parse_date).transpose()?,
+        until: given(&search.until).map(parse_until).transpose()?,
+    };
+
+    if !limiter.0.try_attempt(&user_id) {
+        return Err("You are searching too often, try again in a minute.".to_string());
+    }
+
+    db.search_posts(
+        user,
+        &query,
+        &filters,
+        search.cursor.as_deref(),
+        POSTS_PER_PAGE,
+    )
+}
+
+#[get("/search/users?<q>")]
+fn users(
+    db: &State<DatabaseHandler>,
+    limiter: &State<SearchLimiter>,
+    user: AuthUser,
+    q: &str,
+) -> Result<Json<Vec<PublicUser>>, String> {
+    search_users(db, limiter, &user.0, q).map(Json)
+}
+
+#[get("/search/posts?<search..>")]
+fn posts(
+    db: &State<DatabaseHandler>,
+    limiter: &State<SearchLimiter>,
+    user: AuthUser,
+    search: PostSearch,
+) -> Result<Json<Page<Post>>, String> {
+    search_posts(db, limiter, &user.0, &search).map(Json)
+}
+
+pub fn get_search_routes() -> Vec<Route> {
+    routes![users, posts]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(time: &str) -> DateTime {
+        DateTime::parse_rfc3339_str(time).unwrap()
+    }
+
+    #[test]
+    fn dates_are_days_in_utc() {
+        assert_eq!(
+            parse_date("2024-05-31").unwrap(),
+            utc("2024-05-31T00:00:00Z")
+        );
+        assert!(parse_date("31/05/2024").is_err());
+        assert!(parse_date("2024-05-31T12:00:00Z").is_err());
+        assert!(parse_date("yesterday").is_err());
+    }
+
+    /// Searching until a day finds posts from the very end of that day, but not from the next.
+    #[test]
+    fn until_includes_the_whole_day() {
+        let until = parse_until("2024-05-31").unwrap();
+
+        assert!(utc("2024-05-31T23:59:59Z") < until);
+        assert_eq!(until, utc("2024-06-01T00:00:00Z"));
+        // Only earlier posts are found, see `PostFilters::until`.
+        assert!(utc("2024-06-01T00:00:00Z") >= until);
+
+        // Leap days are days too.
+        assert_eq!(
+            parse_until("2024-02-28").unwrap(),
+            utc("2024-02-29T00:00:00Z")
+        );
+    }
+}
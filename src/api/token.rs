@@ -1,44 +1,129 @@
-use jsonwebtoken::{DecodingKey, EncodingKey, Validation};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation};
+use mongodb::bson::oid::ObjectId;
 use rocket::{
-    http::Status,
+    http::{Cookie, SameSite, Status},
     request::{FromRequest, Outcome, Request},
 };
 use serde::{Deserialize, Serialize};
 
-pub const ENCODING_KEY: EncodingKey = EncodingKey::from_secret("SECRET".as_bytes());
-pub const DECODING_KEY: DecodingKey = DecodingKey::from_secret("SECRET".as_bytes());
+use crate::config::BreadConfig;
+
+/// The name of the cookie that holds the session token.
+pub const TOKEN_COOKIE: &str = "api-token";
+
+/// How long a session token is valid for, in seconds. (One week.)
+pub const TOKEN_LIFETIME: usize = 60 * 60 * 24 * 7;
+
+/// Keys are made from `BreadConfig::jwt_secret`.
+pub fn encoding_key(secret: &str) -> EncodingKey {
+    EncodingKey::from_secret(secret.as_bytes())
+}
+
+pub fn decoding_key(secret: &str) -> DecodingKey {
+    DecodingKey::from_secret(secret.as_bytes())
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub exp: usize,
+    /// The id of the user, never the name, since names can change and be taken by someone else.
     pub sub: String,
 }
 
+impl Claims {
+    /// Create claims for a user that expire `TOKEN_LIFETIME` seconds from now.
+    pub fn new(user_id: &ObjectId) -> Self {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs() as usize)
+            .unwrap_or_default();
+
+        Claims {
+            exp: now + TOKEN_LIFETIME,
+            sub: user_id.to_hex(),
+        }
+    }
+
+    /// The id of the user the claims were made for.
+    pub fn user_id(&self) -> Result<ObjectId, String> {
+        ObjectId::parse_str(&self.sub).map_err(|_| "Invalid API token!".to_string())
+    }
+
+    /// Encode the claims into a session cookie, signed with `secret`.
+    /// The cookie is valid for the whole site, so that both `/app` and `/api` can read it.
+    pub fn into_cookie(self, secret: &str) -> Result<Cookie<'static>, String> {
+        let token = jsonwebtoken::encode(&Header::default(), &self, &encoding_key(secret))
+            .map_err(|err| err.to_string())?;
+
+        Ok(Cookie::build((TOKEN_COOKIE, token))
+            .path("/")
+            .secure(true)
+            .http_only(true)
+            .same_site(SameSite::Lax)
+            .build())
+    }
+}
+
 #[rocket::async_trait]
 impl<'a> FromRequest<'a> for Claims {
     type Error = String;
 
     async fn from_request(request: &'a Request<'_>) -> Outcome<Self, Self::Error> {
+        let config = match request.rocket().state::<BreadConfig>() {
+            Some(config) => config,
+            None => {
+                return Outcome::Error((
+                    Status::InternalServerError,
+                    "No configuration!".to_string(),
+                ))
+            }
+        };
+
         let cookie = request
             .cookies()
-            .get("api-token")
+            .get(TOKEN_COOKIE)
             .map(|cookie| cookie.value());
 
         let token = match cookie {
-            Some(encoded_token) => {
-                jsonwebtoken::decode::<Claims>(encoded_token, &DECODING_KEY, &Validation::default())
-                    .ok()
-                    .map(|token| token.claims)
-            }
+            Some(encoded_token) => jsonwebtoken::decode::<Claims>(
+                encoded_token,
+                &decoding_key(&config.jwt_secret),
+                &Validation::default(),
+            )
+            .ok()
+            .map(|token| token.claims),
             None => None,
         };
 
         match token {
             Some(valid_token) => Outcome::Success(valid_token),
-            None => Outcome::Failure((
+            None => Outcome::Error((
                 Status::Unauthorized,
                 "Invalid or missing API token!".to_string(),
             )),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Make sure a freshly created token can be decoded again, i.e. that it hasn't expired.
+    #[test]
+    fn encode_and_decode_claims() {
+        let secret = "a secret that is only used in this test";
+        let user_id = ObjectId::new();
+        let claims = Claims::new(&user_id);
+        let token = jsonwebtoken::encode(&Header::default(), &claims, &encoding_key(secret))
+            .expect("Could not encode the claims!");
+
+        let decoded =
+            jsonwebtoken::decode::<Claims>(&token, &decoding_key(secret), &Validation::default())
+                .expect("Could not decode the claims!");
+
+        assert_eq!(decoded.claims.user_id(), Ok(user_id));
+    }
+}
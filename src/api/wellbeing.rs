@@ -0,0 +1,263 @@
+use std::time::Duration;
+
+use mongodb::bson::DateTime;
+use rocket::{serde::json::Json, Route, State};
+use serde::Serialize;
+
+use crate::{
+    database::DatabaseHandler,
+    models::{
+        activity::{DailyQuota, LocalTime},
+        user::{QuietHours, User, WellbeingPreferences},
+    },
+    rate_limit::RateLimiter,
+};
+
+use super::{guards::AuthUser, id_of, SettingsForm};
+
+/// How many days a usage summary covers.
+pub const SUMMARY_DAYS: i64 = 7;
+
+/// UTC offsets go from -12:00 to +14:00.
+const MAX_UTC_OFFSET_MINUTES: i32 = 14 * 60;
+
+/// Why a user is asked to rest instead of being shown posts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(crate = "rocket::serde", rename_all = "kebab-case")]
+pub enum RestReason {
+    QuietHours,
+    BudgetUsed,
+}
+
+/// How many minutes a user used Bread on one of their days.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct DayUsage {
+    pub day: String,
+    pub minutes: usize,
+}
+
+/// How much a user has used Bread lately.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct UsageSummary {
+    /// The last days, oldest first and ending with today.
+    pub days: Vec<DayUsage>,
+    pub total_minutes: usize,
+    /// The user's daily budget, if they have set one.
+    pub daily_minutes: Option<u16>,
+}
+
+/// Keeps each user's activity from being written more than once a minute, since every request they make counts as activity.
+pub struct ActivityLimiter(RateLimiter);
+
+impl Default for ActivityLimiter {
+    fn default() -> Self {
+        ActivityLimiter(RateLimiter::new(1, Duration::from_secs(60)))
+    }
+}
+
+/// Note that a user is using Bread right now, unless that has already been noted in the last minute.
+pub fn record_activity(
+    db: &DatabaseHandler,
+    limiter: &ActivityLimiter,
+    user: &User,
+) -> Result<(), String> {
+    let user_id = id_of(user)?;
+    if !limiter.0.try_attempt(&user_id) {
+        return Ok(());
+    }
+
+    let now = LocalTime::at(
+        DateTime::now(),
+        user.preferences.wellbeing.utc_offset_minutes,
+    )?;
+
+    db.record_activity(&user_id, &now.day)
+}
+
+/// The day that daily quotas are counted on right now.
+/// Quotas go by the day in UTC rather than the user's own, since changing `utc_offset_minutes` would otherwise start a fresh day.
+fn quota_day() -> Result<String, String> {
+    Ok(LocalTime::at(DateTime::now(), 0)?.day)
+}
+
+/// Get how much of a daily quota a user has left today.
+pub fn quota_left(
+    db: &DatabaseHandler,
+    user: &User,
+    quota: DailyQuota,
+    limit: u32,
+) -> Result<u32, String> {
+    let used = db.count_quota_used(&id_of(user)?, &quota_day()?, quota)?;
+
+    Ok(limit.saturating_sub(used))
+}
+
+/// Use up one of a user's daily quota. Returns false if they have none left today.
+pub fn take_quota(
+    db: &DatabaseHandler,
+    user: &User,
+    quota: DailyQuota,
+    limit: u32,
+) -> Result<bool, String> {
+    db.take_quota(&id_of(user)?, &quota_day()?, quota, limit)
+}
+
+/// Check if a user should be resting instead of reading posts, because of their quiet hours or daily budget.
+pub fn rest_reason(db: &DatabaseHandler, user: &User) -> Result<Option<RestReason>, String> {
+    let wellbeing = &user.preferences.wellbeing;
+    let now = LocalTime::at(DateTime::now(), wellbeing.utc_offset_minutes)?;
+
+    if wellbeing
+        .quiet_hours
+        .is_some_and(|quiet_hours| quiet_hours.contains(now.hour()))
+    {
+        return Ok(Some(RestReason::QuietHours));
+    }
+
+    if let Some(daily_minutes) = wellbeing.daily_minutes {
+        if db.count_active_minutes(&id_of(user)?, &now.day)? >= usize::from(daily_minutes) {
+            return Ok(Some(RestReason::BudgetUsed));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Sum up how much a user has used Bread on each of the last days.
+pub fn usage_summary(db: &DatabaseHandler, user: &User) -> Result<UsageSummary, String> {
+    let wellbeing = &user.preferences.wellbeing;
+    let days = LocalTime::last_days(DateTime::now(), wellbeing.utc_offset_minutes, SUMMARY_DAYS)?;
+    let activity = db.find_activity(&id_of(user)?, &days)?;
+
+    let days: Vec<DayUsage> = days
+        .into_iter()
+        .map(|day| {
+            let minutes = activity
+                .iter()
+                .find(|activity| activity.day == day)
+                .map_or(0, |activity| activity.active_minutes());
+            DayUsage { day, minutes }
+        })
+        .collect();
+
+    Ok(UsageSummary {
+        total_minutes: days.iter().map(|day| day.minutes).sum(),
+        days,
+        daily_minutes: wellbeing.daily_minutes,
+    })
+}
+
+/// Apply the wellbeing part of a settings change. Settings that are left out stay the same.
+pub fn apply_wellbeing_settings(
+    wellbeing: &mut WellbeingPreferences,
+    settings: &SettingsForm,
+) -> Result<(), String> {
+    if let Some(daily_minutes) = settings.daily_minutes {
+        if daily_minutes > 24 * 60 {
+            return Err("A day only has 1440 minutes!".to_string());
+        }
+        wellbeing.daily_minutes = (daily_minutes > 0).then_some(daily_minutes);
+    }
+
+    match settings.quiet_hours {
+        Some(true) => {
+            let (start, end) = match (settings.quiet_hours_start, settings.quiet_hours_end) {
+                (Some(start), Some(end)) if start < 24 && end < 24 => (start, end),
+                _ => return Err("Quiet hours need a start and an end hour!".to_string()),
+            };
+            if start == end {
+                return Err("Quiet hours can't start and end at the same hour!".to_string());
+            }
+            wellbeing.quiet_hours = Some(QuietHours { start, end });
+        }
+        Some(false) => wellbeing.quiet_hours = None,
+        None => {}
+    }
+
+    if let Some(utc_offset_minutes) = settings.utc_offset_minutes {
+        if utc_offset_minutes.abs() > MAX_UTC_OFFSET_MINUTES {
+            return Err("Invalid time zone!".to_string());
+        }
+        wellbeing.utc_offset_minutes = utc_offset_minutes;
+    }
+
+    Ok(())
+}
+
+#[get("/wellbeing/rest")]
+fn rest(db: &State<DatabaseHandler>, user: AuthUser) -> Result<Json<Option<RestReason>>, String> {
+    rest_reason(db, &user.0).map(Json)
+}
+
+#[get("/wellbeing/summary")]
+fn summary(db: &State<DatabaseHandler>, user: AuthUser) -> Result<Json<UsageSummary>, String> {
+    usage_summary(db, &user.0).map(Json)
+}
+
+pub fn get_wellbeing_routes() -> Vec<Route> {
+    routes![rest, summary]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wellbeing_settings_are_validated() {
+        let mut wellbeing = WellbeingPreferences::default();
+
+        let settings = SettingsForm {
+            daily_minutes: Some(45),
+            quiet_hours: Some(true),
+            quiet_hours_start: Some(22),
+            quiet_hours_end: Some(7),
+            utc_offset_minutes: Some(120),
+            ..Default::default()
+        };
+        assert!(apply_wellbeing_settings(&mut wellbeing, &settings).is_ok());
+        assert_eq!(wellbeing.daily_minutes, Some(45));
+        assert_eq!(
+            wellbeing.quiet_hours,
+            Some(QuietHours { start: 22, end: 7 })
+        );
+        assert_eq!(wellbeing.utc_offset_minutes, 120);
+
+        // Zero minutes and turning quiet hours off remove them.
+        let settings = SettingsForm {
+            daily_minutes: Some(0),
+            quiet_hours: Some(false),
+            ..Default::default()
+        };
+        assert!(apply_wellbeing_settings(&mut wellbeing, &settings).is_ok());
+        assert_eq!(wellbeing.daily_minutes, None);
+        assert_eq!(wellbeing.quiet_hours, None);
+        assert_eq!(wellbeing.utc_offset_minutes, 120);
+
+        for settings in [
+            SettingsForm {
+                quiet_hours: Some(true),
+                quiet_hours_start: Some(24),
+                quiet_hours_end: Some(7),
+                ..Default::default()
+            },
+            SettingsForm {
+                quiet_hours: Some(true),
+                quiet_hours_start: Some(7),
+                quiet_hours_end: Some(7),
+                ..Default::default()
+            },
+            SettingsForm {
+                daily_minutes: Some(24 * 60 + 1),
+                ..Default::default()
+            },
+            SettingsForm {
+                utc_offset_minutes: Some(15 * 60),
+                ..Default::default()
+            },
+        ] {
+            assert!(apply_wellbeing_settings(&mut wellbeing, &settings).is_err());
+        }
+    }
+}
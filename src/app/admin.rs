@@ -0,0 +1,113 @@
+use rocket::{response::Redirect, Route, State};
+use rocket_dyn_templates::{context, Template};
+
+use crate::{
+    api::{
+        admin,
+        guards::{Admin, Moderator},
+        reports, POSTS_PER_PAGE,
+    },
+    database::DatabaseHandler,
+    storage::ImageStorage,
+};
+
+use super::views::post_views;
+
+#[get("/users")]
+fn users(db: &State<DatabaseHandler>, _admin: Admin) -> Result<Template, String> {
+    Ok(Template::render(
+        "app/admin/users",
+        context! { users: admin::list_users(db)? },
+    ))
+}
+
+#[post("/users/<user_id>/suspend")]
+fn suspend(db: &State<DatabaseHandler>, admin: Admin, user_id: &str) -> Result<Redirect, String> {
+    admin::set_suspended(db, &admin.0, user_id, true)?;
+    Ok(Redirect::to(uri!("/app/admin", users)))
+}
+
+#[post("/users/<user_id>/unsuspend")]
+fn unsuspend(db: &State<DatabaseHandler>, admin: Admin, user_id: &str) -> Result<Redirect, String> {
+    admin::set_suspended(db, &admin.0, user_id, false)?;
+    Ok(Redirect::to(uri!("/app/admin", users)))
+}
+
+#[get("/posts?<cursor>")]
+fn posts(
+    db: &State<DatabaseHandler>,
+    _moderator: Moderator,
+    cursor: Option<&str>,
+) -> Result<Template, String> {
+    let page = db.find_latest_posts(cursor, POSTS_PER_PAGE)?;
+
+    Ok(Template::render(
+        "app/admin/posts",
+        context! {
+            posts: post_views(db, page.items)?,
+            next_cursor: page.next_cursor,
+        },
+    ))
+}
+
+#[post("/posts/<post_id>/remove")]
+fn remove_post(
+    db: &State<DatabaseHandler>,
+    storage: &State<ImageStorage>,
+    moderator: Moderator,
+    post_id: &str,
+) -> Result<Redirect, String> {
+    admin::remove_post(db, storage, &moderator.0, post_id)?;
+    Ok(Redirect::to(uri!("/app/admin", posts(None::<&str>))))
+}
+
+#[get("/reports")]
+fn report_queue(db: &State<DatabaseHandler>, _moderator: Moderator) -> Result<Template, String> {
+    Ok(Template::render(
+        "app/admin/reports",
+        context! { reported_posts: reports::report_queue(db)? },
+    ))
+}
+
+#[post("/reports/<post_id>/dismiss")]
+fn dismiss_reports(
+    db: &State<DatabaseHandler>,
+    moderator: Moderator,
+    post_id: &str,
+) -> Result<Redirect, String> {
+    reports::dismiss_reports(db, &moderator.0, post_id)?;
+    Ok(Redirect::to(uri!("/app/admin", report_queue)))
+}
+
+#[post("/reports/<post_id>/remove")]
+fn remove_reported_post(
+    db: &State<DatabaseHandler>,
+    storage: &State<ImageStorage>,
+    moderator: Moderator,
+    post_id: &str,
+) -> Result<Redirect, String> {
+    reports::remove_reported_post(db, storage, &moderator.0, post_id)?;
+    Ok(Redirect::to(uri!("/app/admin", report_queue)))
+}
+
+#[get("/audit")]
+fn audit(db: &State<DatabaseHandler>, _admin: Admin) -> Result<Template, String> {
+    Ok(Template::render(
+        "app/admin/audit",
+        context! { entries: admin::audit_trail(db)? },
+    ))
+}
+
+pub fn get_admin_app_routes() -> Vec<Route> {
+    routes![
+        users,
+        suspend,
+        unsuspend,
+        posts,
+        remove_post,
+        report_queue,
+        dismiss_reports,
+        remove_reported_post,
+        audit
+    ]
+}
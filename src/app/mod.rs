@@ -1,6 +1,78 @@
-use rocket::Route;
+use std::collections::HashMap;
+
+use rocket::{form::Form, http::CookieJar, response::Redirect, Catcher, Request, Route, State};
 use rocket_dyn_templates::{context, Template};
 
+use crate::{
+    api::{
+        self, conversations,
+        guards::AuthUser,
+        reactions,
+        replies::{self, ReplyForm, REPLIES_PER_PAGE},
+        reports, restrictions,
+        search::{self, PostSearch, SearchLimiter},
+        wellbeing, CreatePostForm, SettingsForm, UserForm, POSTS_PER_PAGE,
+    },
+    classifier::PostClassifier,
+    config::BreadConfig,
+    database::DatabaseHandler,
+    metrics::{Event, Metrics},
+    models::{
+        activity::DailyQuota,
+        post::ContentWarning,
+        reaction::ReactionKind,
+        report::ReportReason,
+        restriction::RestrictionKind,
+        user::{ProfileColor, User, WarningPreference},
+    },
+    storage::ImageStorage,
+};
+
+use self::views::{
+    apply_warning_preferences, post_views, reply_views, ConversationView, DateView, MessageView,
+    SearchResultView, UserView,
+};
+
+pub mod admin;
+pub mod views;
+
+/// The reason picked in the report form on the random page.
+#[derive(FromForm)]
+struct ReportForm {
+    reason: ReportReason,
+}
+
+/// The wellbeing settings form. Quiet hours are turned off by leaving the checkbox unchecked.
+#[derive(FromForm)]
+struct WellbeingForm {
+    daily_minutes: u16,
+    quiet_hours: bool,
+    quiet_hours_start: u8,
+    quiet_hours_end: u8,
+    /// Filled in by the browser, so it is missing if scripts are turned off.
+    utc_offset_minutes: Option<i32>,
+}
+
+/// The page shown instead of posts when the user should be resting, or `None` if they don't have to.
+/// Every page for discovering posts or people shows it: friends, random and both searches.
+/// The user's own posts, replies, messages and settings stay reachable, so that a break never cuts anyone off from support.
+fn rest_screen(db: &DatabaseHandler, user: &User) -> Result<Option<Template>, String> {
+    let reason = match wellbeing::rest_reason(db, user)? {
+        Some(reason) => reason,
+        None => return Ok(None),
+    };
+    let wellbeing = &user.preferences.wellbeing;
+
+    Ok(Some(Template::render(
+        "app/rest",
+        context! {
+            reason,
+            quiet_hours: wellbeing.quiet_hours,
+            daily_minutes: wellbeing.daily_minutes,
+        },
+    )))
+}
+
 #[get("/")]
 fn landing() -> Template {
     Template::render("landing", context! {})
@@ -11,52 +83,702 @@ fn login() -> Template {
     Template::render("login", context! {})
 }
 
+#[post("/login", data = "<user>")]
+fn login_submit(
+    db: &State<DatabaseHandler>,
+    config: &State<BreadConfig>,
+    metrics: &State<Metrics>,
+    cookies: &CookieJar<'_>,
+    user: Form<UserForm>,
+) -> Result<Redirect, Box<Template>> {
+    match api::log_in(db, config, metrics, cookies, &user.username, &user.password) {
+        Ok(_) => Ok(Redirect::to(uri!("/app", create_post))),
+        Err(err) => Err(Box::new(Template::render(
+            "login",
+            context! { error: err, username: &user.username },
+        ))),
+    }
+}
+
 #[get("/register")]
 fn register() -> Template {
     Template::render("register", context! {})
 }
 
+#[post("/register", data = "<user>")]
+fn register_submit(
+    db: &State<DatabaseHandler>,
+    config: &State<BreadConfig>,
+    metrics: &State<Metrics>,
+    cookies: &CookieJar<'_>,
+    user: Form<UserForm>,
+) -> Result<Redirect, Box<Template>> {
+    // Log the user in right away, so they don't have to type their credentials twice.
+    let result = db
+        .register_user(&user.username, &user.password, &config.argon2)
+        .and_then(|_| {
+            metrics.count(Event::Registration);
+            api::log_in(db, config, metrics, cookies, &user.username, &user.password)
+        });
+
+    match result {
+        Ok(_) => Ok(Redirect::to(uri!("/app", create_post))),
+        Err(err) => Err(Box::new(Template::render(
+            "register",
+            context! { error: err, username: &user.username },
+        ))),
+    }
+}
+
 #[get("/create-post")]
-fn create_post() -> Template {
-    Template::render(
+fn create_post(
+    db: &State<DatabaseHandler>,
+    config: &State<BreadConfig>,
+    user: AuthUser,
+) -> Result<Template, String> {
+    Ok(Template::render(
         "app/create-post",
         context! {
-            username: "FooBar",
-            posts_left: 1,
-            posts_per_day: 2,
+            posts_left: wellbeing::quota_left(db, &user.0, DailyQuota::Posts, config.posts_per_day)?,
+            posts_per_day: config.posts_per_day,
+            username: user.0.name,
         },
-    )
+    ))
+}
+
+#[post("/create-post", data = "<post>")]
+async fn create_post_submit(
+    db: &State<DatabaseHandler>,
+    config: &State<BreadConfig>,
+    classifier: &State<PostClassifier>,
+    storage: &State<ImageStorage>,
+    metrics: &State<Metrics>,
+    user: AuthUser,
+    mut post: Form<CreatePostForm<'_>>,
+) -> Result<Template, String> {
+    let result =
+        api::publish_post(db, config, classifier, storage, metrics, &user.0, &mut post).await;
+
+    // On failure the content is kept, so that the user doesn't lose what they wrote.
+    let (published, error, content, crisis_response) = match result {
+        Ok(created) => (true, None, None, created.crisis_response),
+        Err(err) => (false, Some(err), post.content.clone(), None),
+    };
+
+    Ok(Template::render(
+        "app/create-post",
+        context! {
+            posts_left: wellbeing::quota_left(db, &user.0, DailyQuota::Posts, config.posts_per_day)?,
+            posts_per_day: config.posts_per_day,
+            username: user.0.name,
+            published,
+            error,
+            content,
+            crisis_response,
+        },
+    ))
 }
 
-#[get("/friends")]
-fn friends() -> Template {
-    Template::render("app/friends", context! {})
+#[get("/friends?<cursor>")]
+fn friends(
+    db: &State<DatabaseHandler>,
+    user: AuthUser,
+    cursor: Option<&str>,
+) -> Result<Template, String> {
+    if let Some(rest) = rest_screen(db, &user.0)? {
+        return Ok(rest);
+    }
+
+    let followees = db.find_followees(&api::id_of(&user.0)?)?;
+    let users: Vec<UserView> = db
+        .find_users_by_ids(&followees)?
+        .iter()
+        .map(UserView::from)
+        .collect();
+    let feed = api::friends_feed(db, &user.0, cursor)?;
+
+    Ok(Template::render(
+        "app/friends",
+        context! {
+            users,
+            posts: apply_warning_preferences(post_views(db, feed.items)?, &user.0),
+            next_cursor: feed.next_cursor,
+        },
+    ))
 }
 
-#[get("/random")]
-fn random() -> Template {
-    Template::render(
+#[get("/random?<topics>")]
+fn random(
+    db: &State<DatabaseHandler>,
+    config: &State<BreadConfig>,
+    metrics: &State<Metrics>,
+    user: AuthUser,
+    topics: Option<bool>,
+) -> Result<Template, String> {
+    if let Some(rest) = rest_screen(db, &user.0)? {
+        return Ok(rest);
+    }
+
+    let user_id = api::id_of(&user.0)?;
+    let in_topics = topics.unwrap_or(false);
+    let found = match &user.0.preferences.topics {
+        topics if in_topics && topics.is_empty() => {
+            Err("You haven't picked any topics yet. Pick some on your profile!".to_string())
+        }
+        topics if in_topics => db.find_random_post_in_topics(&user_id, topics),
+        _ => db.find_random_post(&user_id),
+    };
+
+    // Only posts that are shown use up the quota, so that a missing post doesn't cost anything.
+    let limit = config.random_posts_per_day;
+    let found = match found {
+        Ok(_) if !wellbeing::take_quota(db, &user.0, DailyQuota::RandomPosts, limit)? => {
+            Err(format!(
+                "You have seen your {} random posts for today, come back tomorrow!",
+                limit
+            ))
+        }
+        found => found,
+    };
+
+    // Not finding a post isn't fatal, e.g. there may not be any posts yet.
+    let (post, error) = match found {
+        Ok(post) => (
+            apply_warning_preferences(post_views(db, vec![post])?, &user.0).pop(),
+            None,
+        ),
+        Err(err) => (None, Some(err)),
+    };
+
+    if post.is_some() {
+        metrics.count(Event::RandomPostServed);
+    }
+
+    let (following, reaction) = match &post {
+        Some(post) => (
+            db.is_following(&user_id, &api::parse_object_id(&post.author.id)?)?,
+            db.find_reaction(&api::parse_object_id(&post.id)?, &user_id)?
+                .map(|reaction| reaction.kind),
+        ),
+        None => (false, None),
+    };
+
+    Ok(Template::render(
         "app/random",
         context! {
-            posts_left: 3,
-            posts_per_day: 10,
+            post,
+            error,
+            following,
+            reaction,
+            reaction_kinds: ReactionKind::ALL,
+            in_topics,
+            topics: &user.0.preferences.topics,
+            posts_left: wellbeing::quota_left(db, &user.0, DailyQuota::RandomPosts, limit)?,
+            posts_per_day: limit,
+        },
+    ))
+}
+
+#[post("/follow/<user_id>")]
+fn follow(db: &State<DatabaseHandler>, user: AuthUser, user_id: &str) -> Result<Redirect, String> {
+    api::follow_user(db, &user.0, user_id)?;
+    Ok(Redirect::to(uri!("/app", friends(_))))
+}
+
+#[post("/unfollow/<user_id>")]
+fn unfollow(
+    db: &State<DatabaseHandler>,
+    user: AuthUser,
+    user_id: &str,
+) -> Result<Redirect, String> {
+    api::unfollow_user(db, &user.0, user_id)?;
+    Ok(Redirect::to(uri!("/app", friends(_))))
+}
+
+#[post("/block/<user_id>")]
+fn block(db: &State<DatabaseHandler>, user: AuthUser, user_id: &str) -> Result<Redirect, String> {
+    restrictions::restrict_user(db, &user.0, user_id, RestrictionKind::Block)?;
+    Ok(Redirect::to(uri!("/app", random(_))))
+}
+
+#[post("/mute/<user_id>")]
+fn mute(db: &State<DatabaseHandler>, user: AuthUser, user_id: &str) -> Result<Redirect, String> {
+    restrictions::restrict_user(db, &user.0, user_id, RestrictionKind::Mute)?;
+    Ok(Redirect::to(uri!("/app", random(_))))
+}
+
+#[post("/unblock/<user_id>")]
+fn unblock(db: &State<DatabaseHandler>, user: AuthUser, user_id: &str) -> Result<Redirect, String> {
+    restrictions::unrestrict_user(db, &user.0, user_id, RestrictionKind::Block)?;
+    Ok(Redirect::to(uri!("/app", restricted_users)))
+}
+
+#[post("/unmute/<user_id>")]
+fn unmute(db: &State<DatabaseHandler>, user: AuthUser, user_id: &str) -> Result<Redirect, String> {
+    restrictions::unrestrict_user(db, &user.0, user_id, RestrictionKind::Mute)?;
+    Ok(Redirect::to(uri!("/app", restricted_users)))
+}
+
+#[get("/search?<q>")]
+fn search_page(
+    db: &State<DatabaseHandler>,
+    limiter: &State<SearchLimiter>,
+    user: AuthUser,
+    q: Option<&str>,
+) -> Result<Template, String> {
+    if let Some(rest) = rest_screen(db, &user.0)? {
+        return Ok(rest);
+    }
+
+    let user_id = api::id_of(&user.0)?;
+    let query = q.unwrap_or_default().trim();
+
+    // A search that isn't allowed, e.g. because it is too short, is shown on the page.
+    let (users, error) = match query {
+        "" => (vec![], None),
+        query => match search::search_users(db, limiter, &user.0, query) {
+            Ok(users) => (users, None),
+            Err(err) => (vec![], Some(err)),
+        },
+    };
+    let results = users
+        .into_iter()
+        .map(|found| SearchResultView::new(db, &user_id, found))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Template::render(
+        "app/search",
+        context! { query, results, error },
+    ))
+}
+
+#[get("/search/posts?<q>&<since>&<until>&<cursor>")]
+fn search_posts_page(
+    db: &State<DatabaseHandler>,
+    limiter: &State<SearchLimiter>,
+    user: AuthUser,
+    q: Option<&str>,
+    since: Option<&str>,
+    until: Option<&str>,
+    cursor: Option<&str>,
+) -> Result<Template, String> {
+    if let Some(rest) = rest_screen(db, &user.0)? {
+        return Ok(rest);
+    }
+
+    let query = q.unwrap_or_default().trim();
+    let search = PostSearch {
+        q: query.to_string(),
+        author: None,
+        since: since.map(str::to_string),
+        until: until.map(str::to_string),
+        cursor: cursor.map(str::to_string),
+    };
+
+    // A search that isn't allowed, e.g. because a date is wrong, is shown on the page.
+    let (posts, next_cursor, error) = match query {
+        "" => (vec![], None, None),
+        _ => match search::search_posts(db, limiter, &user.0, &search) {
+            Ok(page) => (
+                apply_warning_preferences(post_views(db, page.items)?, &user.0),
+                page.next_cursor,
+                None,
+            ),
+            Err(err) => (vec![], None, Some(err)),
+        },
+    };
+
+    Ok(Template::render(
+        "app/search-posts",
+        context! {
+            query,
+            since: since.unwrap_or_default(),
+            until: until.unwrap_or_default(),
+            posts,
+            next_cursor,
+            error,
+        },
+    ))
+}
+
+#[get("/profile/blocked")]
+fn restricted_users(db: &State<DatabaseHandler>, user: AuthUser) -> Result<Template, String> {
+    let restrictions = restrictions::find_restrictions(db, &user.0)?;
+    let blocked: Vec<UserView> = restrictions
+        .blocked
+        .into_iter()
+        .map(UserView::from)
+        .collect();
+    let muted: Vec<UserView> = restrictions.muted.into_iter().map(UserView::from).collect();
+
+    Ok(Template::render("app/blocked", context! { blocked, muted }))
+}
+
+#[post("/report/<post_id>", data = "<report>")]
+fn report(
+    db: &State<DatabaseHandler>,
+    user: AuthUser,
+    post_id: &str,
+    report: Form<ReportForm>,
+) -> Result<Redirect, String> {
+    reports::report_post(db, &user.0, post_id, report.reason)?;
+    Ok(Redirect::to(uri!("/app", random(_))))
+}
+
+#[post("/react/<post_id>/<kind>")]
+fn react(
+    db: &State<DatabaseHandler>,
+    user: AuthUser,
+    post_id: &str,
+    kind: ReactionKind,
+) -> Result<Redirect, String> {
+    reactions::react_to_post(db, &user.0, post_id, kind)?;
+    Ok(Redirect::to(uri!("/app", random(_))))
+}
+
+#[post("/unreact/<post_id>")]
+fn unreact(db: &State<DatabaseHandler>, user: AuthUser, post_id: &str) -> Result<Redirect, String> {
+    reactions::remove_reaction(db, &user.0, post_id)?;
+    Ok(Redirect::to(uri!("/app", random(_))))
+}
+
+#[post("/reply/<post_id>", data = "<reply>")]
+fn reply(
+    db: &State<DatabaseHandler>,
+    user: AuthUser,
+    post_id: &str,
+    reply: Form<ReplyForm>,
+) -> Result<Redirect, String> {
+    replies::send_reply(db, &user.0, post_id, &reply.content)?;
+    Ok(Redirect::to(uri!("/app", random(_))))
+}
+
+#[get("/replies?<cursor>")]
+fn replies_page(
+    db: &State<DatabaseHandler>,
+    user: AuthUser,
+    cursor: Option<&str>,
+) -> Result<Template, String> {
+    let page = db.find_replies_to(&api::id_of(&user.0)?, cursor, REPLIES_PER_PAGE)?;
+
+    Ok(Template::render(
+        "app/replies",
+        context! {
+            replies: reply_views(db, page.items)?,
+            next_cursor: page.next_cursor,
+        },
+    ))
+}
+
+#[post("/replies/<reply_id>/read")]
+fn read_reply(
+    db: &State<DatabaseHandler>,
+    user: AuthUser,
+    reply_id: &str,
+) -> Result<Redirect, String> {
+    replies::mark_read(db, &user.0, reply_id)?;
+    Ok(Redirect::to(uri!("/app", replies_page(_))))
+}
+
+/// The settings form on the profile page.
+/// Unlike the API, the page always sends every setting.
+#[derive(FromForm)]
+struct ProfileForm {
+    username: String,
+    #[field(name = "profile-color")]
+    profile_color: ProfileColor,
+    send_read_receipts: bool,
+    is_private: bool,
+    topics: String,
+    content_warnings: HashMap<ContentWarning, WarningPreference>,
+}
+
+/// Things shown on the profile page that have to be looked up in the database.
+#[derive(Default)]
+struct ProfileExtras {
+    unread_replies: u64,
+    follow_requests: Vec<UserView>,
+}
+
+impl ProfileExtras {
+    fn find(db: &DatabaseHandler, user: &User) -> Result<Self, String> {
+        Ok(ProfileExtras {
+            unread_replies: db.count_unread_replies(&api::id_of(user)?)?,
+            follow_requests: api::follow_requests(db, user)?
+                .iter()
+                .map(UserView::from)
+                .collect(),
+        })
+    }
+}
+
+/// Render the profile page, optionally with an error from the settings form.
+fn profile_page(user: &User, extras: ProfileExtras, error: Option<String>) -> Template {
+    let content_warnings: Vec<_> = ContentWarning::ALL
+        .into_iter()
+        .map(|warning| {
+            context! {
+                warning,
+                preference: user.preferences.warning_preference(warning),
+            }
+        })
+        .collect();
+
+    Template::render(
+        "app/profile",
+        context! {
+            username: &user.name,
+            profile_color: UserView::from(user).color,
+            send_read_receipts: user.preferences.send_read_receipts,
+            is_private: user.preferences.is_private,
+            topics: &user.preferences.topics,
+            content_warnings,
+            unread_replies: extras.unread_replies,
+            follow_requests: extras.follow_requests,
+            error,
         },
     )
 }
 
+#[get("/inbox")]
+fn inbox(db: &State<DatabaseHandler>, user: AuthUser) -> Result<Template, String> {
+    let conversations: Vec<ConversationView> = conversations::inbox(db, &user.0)?
+        .into_iter()
+        .map(ConversationView::from)
+        .collect();
+    let mutuals: Vec<UserView> = conversations::find_mutual_followers(db, &user.0)?
+        .iter()
+        .map(UserView::from)
+        .collect();
+
+    Ok(Template::render(
+        "app/inbox",
+        context! { conversations, mutuals },
+    ))
+}
+
+#[post("/inbox/with/<user_id>")]
+fn start_conversation(
+    db: &State<DatabaseHandler>,
+    user: AuthUser,
+    user_id: &str,
+) -> Result<Redirect, String> {
+    let conversation = conversations::start_conversation(db, &user.0, user_id)?;
+    let id = conversation
+        .id
+        .ok_or("Conversation not found!")?
+        .to_string();
+
+    Ok(Redirect::to(uri!("/app", conversation(id, _))))
+}
+
+#[get("/inbox/<conversation_id>?<cursor>")]
+fn conversation(
+    db: &State<DatabaseHandler>,
+    user: AuthUser,
+    conversation_id: &str,
+    cursor: Option<&str>,
+) -> Result<Template, String> {
+    let history = conversations::read_conversation(db, &user.0, conversation_id, cursor)?;
+    // Only say that the other user has seen the conversation if they have read the viewer's latest message,
+    // which is on the first page.
+    let seen_at = match (history.messages.items.first(), history.read_by_other_at) {
+        (Some(latest), Some(read_at))
+            if cursor.is_none()
+                && Some(latest.sender) == user.0.id
+                && read_at >= latest.created_at =>
+        {
+            Some(DateView::from(read_at))
+        }
+        _ => None,
+    };
+    // Pages are newest first, but conversations are read from the top down.
+    let messages: Vec<_> = history
+        .messages
+        .items
+        .into_iter()
+        .rev()
+        .map(|message| MessageView::new(message, &user.0))
+        .collect();
+
+    Ok(Template::render(
+        "app/conversation",
+        context! {
+            id: conversation_id,
+            me: UserView::from(&user.0),
+            with: UserView::from(history.with),
+            messages,
+            next_cursor: history.messages.next_cursor,
+            seen_at,
+        },
+    ))
+}
+
 #[get("/profile")]
-fn profile() -> Template {
-    Template::render("app/profile", context! { username: "FooBar" })
+fn profile(db: &State<DatabaseHandler>, user: AuthUser) -> Result<Template, String> {
+    let extras = ProfileExtras::find(db, &user.0)?;
+    Ok(profile_page(&user.0, extras, None))
+}
+
+#[post("/follow-requests/<user_id>/accept")]
+fn accept_follow_request(
+    db: &State<DatabaseHandler>,
+    user: AuthUser,
+    user_id: &str,
+) -> Result<Redirect, String> {
+    api::answer_follow_request(db, &user.0, user_id, true)?;
+    Ok(Redirect::to(uri!("/app", profile)))
+}
+
+#[post("/follow-requests/<user_id>/decline")]
+fn decline_follow_request(
+    db: &State<DatabaseHandler>,
+    user: AuthUser,
+    user_id: &str,
+) -> Result<Redirect, String> {
+    api::answer_follow_request(db, &user.0, user_id, false)?;
+    Ok(Redirect::to(uri!("/app", profile)))
+}
+
+#[post("/profile", data = "<form>")]
+fn profile_submit(
+    db: &State<DatabaseHandler>,
+    user: AuthUser,
+    form: Form<ProfileForm>,
+) -> Result<Redirect, Box<Template>> {
+    let settings = SettingsForm {
+        username: Some(form.username.clone()),
+        profile_color: Some(form.profile_color),
+        prefers_darkmode: None,
+        send_read_receipts: Some(form.send_read_receipts),
+        is_private: Some(form.is_private),
+        topics: Some(form.topics.clone()),
+        content_warnings: form.content_warnings.clone(),
+        ..Default::default()
+    };
+
+    match api::change_settings(db, &user.0, &settings) {
+        Ok(()) => Ok(Redirect::to(uri!("/app", profile))),
+        Err(err) => {
+            // The extras are only a nicety, so failing to get them shouldn't hide the actual error.
+            let extras = ProfileExtras::find(db, &user.0).unwrap_or_default();
+            Err(Box::new(profile_page(&user.0, extras, Some(err))))
+        }
+    }
+}
+
+/// Render the wellbeing page, optionally with an error from the settings form.
+fn wellbeing_page(
+    db: &DatabaseHandler,
+    user: &User,
+    error: Option<String>,
+) -> Result<Template, String> {
+    let wellbeing = &user.preferences.wellbeing;
+
+    Ok(Template::render(
+        "app/wellbeing",
+        context! {
+            daily_minutes: wellbeing.daily_minutes.unwrap_or(0),
+            quiet_hours: wellbeing.quiet_hours,
+            utc_offset_minutes: wellbeing.utc_offset_minutes,
+            summary: wellbeing::usage_summary(db, user)?,
+            error,
+        },
+    ))
+}
+
+#[get("/profile/wellbeing")]
+fn wellbeing_settings(db: &State<DatabaseHandler>, user: AuthUser) -> Result<Template, String> {
+    wellbeing_page(db, &user.0, None)
+}
+
+#[post("/profile/wellbeing", data = "<form>")]
+fn wellbeing_submit(
+    db: &State<DatabaseHandler>,
+    user: AuthUser,
+    form: Form<WellbeingForm>,
+) -> Result<Result<Redirect, Box<Template>>, String> {
+    let settings = SettingsForm {
+        daily_minutes: Some(form.daily_minutes),
+        quiet_hours: Some(form.quiet_hours),
+        quiet_hours_start: Some(form.quiet_hours_start),
+        quiet_hours_end: Some(form.quiet_hours_end),
+        utc_offset_minutes: form.utc_offset_minutes,
+        ..Default::default()
+    };
+
+    match api::change_settings(db, &user.0, &settings) {
+        Ok(()) => Ok(Ok(Redirect::to(uri!("/app", wellbeing_settings)))),
+        Err(err) => Ok(Err(Box::new(wellbeing_page(db, &user.0, Some(err))?))),
+    }
+}
+
+#[get("/profile/posts?<cursor>")]
+fn profile_posts(
+    db: &State<DatabaseHandler>,
+    user: AuthUser,
+    cursor: Option<&str>,
+) -> Result<Template, String> {
+    let page = db.find_posts_by_author(&api::id_of(&user.0)?, cursor, POSTS_PER_PAGE)?;
+    let posts = apply_warning_preferences(post_views(db, page.items)?, &user.0);
+    let post_ids: Vec<&str> = posts.iter().map(|post| post.id.as_str()).collect();
+    let reactions = reactions::received_reactions(db, &user.0, &post_ids)?;
+
+    Ok(Template::render(
+        "app/my-posts",
+        context! {
+            posts,
+            reactions,
+            next_cursor: page.next_cursor,
+        },
+    ))
+}
+
+/// Pages that require a session send the user to the login page instead of showing an error.
+#[catch(401)]
+fn unauthorized(_request: &Request) -> Redirect {
+    Redirect::to(uri!("/app", login))
+}
+
+pub fn get_app_catchers() -> Vec<Catcher> {
+    catchers![unauthorized]
 }
 
 pub fn get_app_routes() -> Vec<Route> {
     routes![
         landing,
         register,
+        register_submit,
         login,
+        login_submit,
         create_post,
+        create_post_submit,
         friends,
         random,
-        profile
+        follow,
+        unfollow,
+        report,
+        block,
+        mute,
+        unblock,
+        unmute,
+        restricted_users,
+        search_page,
+        search_posts_page,
+        react,
+        unreact,
+        reply,
+        replies_page,
+        read_reply,
+        inbox,
+        start_conversation,
+        conversation,
+        profile,
+        profile_submit,
+        accept_follow_request,
+        decline_follow_request,
+        wellbeing_settings,
+        wellbeing_submit,
+        profile_posts
     ]
 }
@@ -0,0 +1,318 @@
+use mongodb::bson::{oid::ObjectId, DateTime};
+use serde::Serialize;
+
+use crate::{
+    api::{conversations::ConversationSummary, parse_object_id, PublicUser},
+    database::DatabaseHandler,
+    models::{
+        conversation::{Envelope, Message},
+        follow::FollowStatus,
+        post::{ContentWarning, Post},
+        reply::Reply,
+        user::{ProfileColor, User, WarningPreference},
+    },
+};
+
+/// A user as shown in the templates.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct UserView {
+    pub id: String,
+    pub name: String,
+    /// The CSS class of the user's profile color.
+    pub color: &'static str,
+}
+
+/// The CSS class of a profile color.
+fn color_class(color: ProfileColor) -> &'static str {
+    match color {
+        ProfileColor::Orange => "orange",
+        ProfileColor::Red => "red",
+        ProfileColor::Green => "green",
+        ProfileColor::Blue => "blue",
+        ProfileColor::Grey => "grey",
+    }
+}
+
+impl From<&User> for UserView {
+    fn from(user: &User) -> Self {
+        UserView {
+            id: user.id.map(|id| id.to_string()).unwrap_or_default(),
+            name: user.name.clone(),
+            color: color_class(user.preferences.profile_color),
+        }
+    }
+}
+
+impl From<PublicUser> for UserView {
+    fn from(user: PublicUser) -> Self {
+        UserView {
+            id: user.id,
+            name: user.name,
+            color: color_class(user.profile_color),
+        }
+    }
+}
+
+/// Show posts the way a viewer wants posts with content warnings shown.
+/// Posts with warnings the viewer hides are left out, and posts with warnings they expand are expanded.
+/// The viewer's own posts are never left out, only collapsed, so that they can always find what they wrote.
+pub fn apply_warning_preferences(posts: Vec<PostView>, viewer: &User) -> Vec<PostView> {
+    let viewer_id = viewer.id.map(|id| id.to_string()).unwrap_or_default();
+
+    posts
+        .into_iter()
+        .filter_map(|mut post| {
+            match post
+                .content_warning
+                .map(|warning| viewer.preferences.warning_preference(warning))
+            {
+                Some(WarningPreference::Hide) if post.author.id != viewer_id => return None,
+                Some(WarningPreference::Expand) => post.expanded = true,
+                _ => {}
+            }
+
+            Some(post)
+        })
+        .collect()
+}
+
+/// A user found by a search, and whether the viewer follows them or has asked to.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct SearchResultView {
+    pub user: UserView,
+    pub follow: Option<FollowStatus>,
+}
+
+impl SearchResultView {
+    pub fn new(db: &DatabaseHandler, viewer: &ObjectId, user: PublicUser) -> Result<Self, String> {
+        let follow = db
+            .find_follow(viewer, &parse_object_id(&user.id)?)?
+            .map(|follow| follow.status);
+
+        Ok(SearchResultView {
+            user: UserView::from(user),
+            follow,
+        })
+    }
+}
+
+/// A date as shown in the templates.
+/// `utc` is shown until the browser has replaced it with the date in the viewer's own time zone.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct DateView {
+    pub iso: String,
+    pub utc: String,
+}
+
+impl From<DateTime> for DateView {
+    fn from(date: DateTime) -> Self {
+        let iso = date.try_to_rfc3339_string().unwrap_or_default();
+        // "2023-03-03T19:02:00Z" becomes "2023-03-03 19:02 UTC".
+        let utc = format!(
+            "{} UTC",
+            iso.replacen('T', " ", 1).get(..16).unwrap_or(&iso)
+        );
+
+        DateView { iso, utc }
+    }
+}
+
+/// A post as shown in the templates, together with its author.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct PostView {
+    pub id: String,
+    pub author: UserView,
+    pub content: Option<String>,
+    /// The URL of the post's image.
+    pub image: Option<String>,
+    pub created_at: DateView,
+    pub edited_at: Option<DateView>,
+    pub expires_at: Option<DateView>,
+    pub content_warning: Option<ContentWarning>,
+    /// Whether a post with a content warning is shown expanded instead of collapsed.
+    pub expanded: bool,
+}
+
+/// Turn posts into views, looking up all authors at once.
+/// Posts whose author no longer exists are left out.
+pub fn post_views(db: &DatabaseHandler, posts: Vec<Post>) -> Result<Vec<PostView>, String> {
+    let author_ids: Vec<_> = posts.iter().map(|post| post.author).collect();
+    let authors = db.find_users_by_ids(&author_ids)?;
+
+    Ok(posts
+        .into_iter()
+        .filter_map(|post| {
+            let author = authors.iter().find(|user| user.id == Some(post.author))?;
+            Some(PostView::new(post, author))
+        })
+        .collect())
+}
+
+impl PostView {
+    /// Show a post by the given author, collapsed if it has a content warning.
+    fn new(post: Post, author: &User) -> Self {
+        PostView {
+            id: post.id.map(|id| id.to_string()).unwrap_or_default(),
+            author: UserView::from(author),
+            content: post.content,
+            image: post.image.map(|image| format!("/images/{}", image)),
+            created_at: DateView::from(post.created_at),
+            edited_at: post.edited_at.map(DateView::from),
+            expires_at: post.expires_at.map(DateView::from),
+            content_warning: post.content_warning,
+            expanded: false,
+        }
+    }
+}
+
+/// A reply as shown in the author's inbox, together with the post it replies to.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct ReplyView {
+    pub id: String,
+    pub from: UserView,
+    /// The content of the post that was replied to, if the post still exists.
+    pub post_content: Option<String>,
+    pub content: String,
+    pub created_at: DateView,
+    pub read: bool,
+}
+
+/// Turn replies into views, looking up all senders and posts at once.
+/// Replies whose sender no longer exists are left out.
+pub fn reply_views(db: &DatabaseHandler, replies: Vec<Reply>) -> Result<Vec<ReplyView>, String> {
+    let sender_ids: Vec<_> = replies.iter().map(|reply| reply.from).collect();
+    let senders = db.find_users_by_ids(&sender_ids)?;
+    let post_ids: Vec<_> = replies.iter().map(|reply| reply.post).collect();
+    let posts = db.find_posts_by_ids(&post_ids)?;
+
+    Ok(replies
+        .into_iter()
+        .filter_map(|reply| {
+            let sender = senders.iter().find(|user| user.id == Some(reply.from))?;
+            let post = posts.iter().find(|post| post.id == Some(reply.post));
+
+            Some(ReplyView {
+                id: reply.id.map(|id| id.to_string()).unwrap_or_default(),
+                from: UserView::from(sender),
+                post_content: post.map(|post| post.content.clone().unwrap_or_default()),
+                content: reply.content,
+                created_at: DateView::from(reply.created_at),
+                read: reply.read,
+            })
+        })
+        .collect())
+}
+
+/// A conversation as listed in the inbox.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct ConversationView {
+    pub id: String,
+    pub with: UserView,
+    pub last_message_at: Option<DateView>,
+    pub unread: bool,
+}
+
+impl From<ConversationSummary> for ConversationView {
+    fn from(conversation: ConversationSummary) -> Self {
+        ConversationView {
+            id: conversation.id,
+            with: UserView::from(conversation.with),
+            last_message_at: conversation.last_message_at.map(DateView::from),
+            unread: conversation.unread,
+        }
+    }
+}
+
+/// A message as shown in a conversation.
+/// The page decrypts it in the browser, using the envelope for the viewer's device.
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct MessageView {
+    /// The envelopes addressed to the viewer's devices.
+    pub envelopes: Vec<Envelope>,
+    /// Whether the viewer sent the message.
+    pub mine: bool,
+    pub created_at: DateView,
+}
+
+impl MessageView {
+    pub fn new(message: Message, viewer: &User) -> Self {
+        MessageView {
+            mine: Some(message.sender) == viewer.id,
+            envelopes: message
+                .envelopes
+                .into_iter()
+                .filter(|envelope| {
+                    viewer
+                        .devices
+                        .iter()
+                        .any(|device| device.id == envelope.device)
+                })
+                .collect(),
+            created_at: DateView::from(message.created_at),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::user::{test_user, Role};
+
+    use super::*;
+
+    fn user(name: &str) -> User {
+        test_user(name, Role::User)
+    }
+
+    fn post(author: &User, warning: Option<ContentWarning>) -> PostView {
+        let mut post = Post::create(author.id.unwrap(), Some("Hello".to_string()), None);
+        post.content_warning = warning;
+        PostView::new(post, author)
+    }
+
+    #[test]
+    fn warning_preferences_are_applied() {
+        let author = user("Author");
+        let mut viewer = user("Viewer");
+        viewer
+            .preferences
+            .set_warning_preference(ContentWarning::Grief, WarningPreference::Hide);
+        viewer
+            .preferences
+            .set_warning_preference(ContentWarning::Abuse, WarningPreference::Expand);
+
+        let shown = apply_warning_preferences(
+            vec![
+                post(&author, None),
+                post(&author, Some(ContentWarning::Grief)),
+                post(&author, Some(ContentWarning::Abuse)),
+                post(&author, Some(ContentWarning::Suicide)),
+            ],
+            &viewer,
+        );
+        let warnings: Vec<_> = shown
+            .iter()
+            .map(|post| (post.content_warning, post.expanded))
+            .collect();
+        assert_eq!(
+            warnings,
+            vec![
+                (None, false),
+                (Some(ContentWarning::Abuse), true),
+                (Some(ContentWarning::Suicide), false),
+            ]
+        );
+
+        // The viewer's own posts are only collapsed.
+        let own =
+            apply_warning_preferences(vec![post(&viewer, Some(ContentWarning::Grief))], &viewer);
+        assert_eq!(own.len(), 1);
+        assert!(!own[0].expanded);
+    }
+}
@@ -0,0 +1,434 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use bread::{
+    api::id_of,
+    config::BreadConfig,
+    database::DatabaseHandler,
+    models::{
+        audit::{AuditEntry, ModerationAction},
+        user::{Role, User},
+    },
+    startup::{self, StartupError},
+    storage::ImageStorage,
+    sweeper,
+};
+use clap::{Parser, Subcommand};
+use mongodb::bson::{oid::ObjectId, Bson};
+use password_hash::rand_core::{OsRng, RngCore};
+use rocket::serde::json::serde_json::{self, Value};
+
+/// Made up passwords are picked from these, so that each character is equally likely.
+const PASSWORD_CHARS: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// 20 characters of 64 kinds make 120 random bits.
+const PASSWORD_LENGTH: usize = 20;
+
+/// Administer Bread from the command line. Reads the same configuration and `MONGO_URI` as Bread itself.
+#[derive(Debug, Parser)]
+#[command(name = "bread-admin", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Create, delete and change users.
+    #[command(subcommand)]
+    User(UserCommand),
+    /// Apply the pending database migrations.
+    Migrate,
+    /// Delete expired posts and their images now, instead of waiting for Bread to do it.
+    PurgeExpired,
+    /// Write every document in the database to a JSON file. Images aren't included.
+    Export { file: PathBuf },
+    /// Load a file written by `export` into a database whose collections are empty.
+    Import { file: PathBuf },
+}
+
+#[derive(Debug, Subcommand)]
+enum UserCommand {
+    /// Create a user. A password is made up and printed, unless one is given with `--password-stdin`.
+    Create {
+        name: String,
+        #[arg(long, value_parser = parse_role, default_value = "user")]
+        role: Role,
+        /// Read the password from the first line of standard input.
+        #[arg(long)]
+        password_stdin: bool,
+    },
+    /// Delete a user along with their posts, images and everything else they have made.
+    Delete { name: String },
+    /// Give a user a new password. A password is made up and printed, unless one is given with `--password-stdin`.
+    ResetPassword {
+        name: String,
+        /// Read the password from the first line of standard input.
+        #[arg(long)]
+        password_stdin: bool,
+    },
+    /// Change what a user is allowed to do. Makes the user an admin, unless another role is given.
+    Promote {
+        name: String,
+        #[arg(long, value_parser = parse_role, default_value = "admin")]
+        role: Role,
+    },
+}
+
+/// Why a command failed.
+enum AdminError {
+    /// The command couldn't start, e.g. because the database couldn't be reached.
+    Startup(StartupError),
+    Command(String),
+}
+
+impl From<StartupError> for AdminError {
+    fn from(err: StartupError) -> Self {
+        AdminError::Startup(err)
+    }
+}
+
+impl From<String> for AdminError {
+    fn from(err: String) -> Self {
+        AdminError::Command(err)
+    }
+}
+
+fn parse_role(role: &str) -> Result<Role, String> {
+    match role {
+        "user" => Ok(Role::User),
+        "moderator" => Ok(Role::Moderator),
+        "admin" => Ok(Role::Admin),
+        _ => Err("The role must be user, moderator or admin".to_string()),
+    }
+}
+
+/// Make up a password for someone to change once they have logged in.
+fn generate_password() -> String {
+    let mut bytes = [0u8; PASSWORD_LENGTH];
+    OsRng.fill_bytes(&mut bytes);
+
+    bytes
+        .iter()
+        .map(|byte| PASSWORD_CHARS[usize::from(byte % 64)] as char)
+        .collect()
+}
+
+/// Read a password from the first line of standard input, so that it never ends up in the shell's history.
+fn read_password() -> Result<String, String> {
+    let mut line = String::new();
+    io::stdin()
+        .read_line(&mut line)
+        .map_err(|err| err.to_string())?;
+
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// The password given on standard input, or a made up one that has to be shown.
+fn new_password(password_stdin: bool) -> Result<(String, bool), String> {
+    if password_stdin {
+        Ok((read_password()?, false))
+    } else {
+        Ok((generate_password(), true))
+    }
+}
+
+fn find_user(db: &DatabaseHandler, name: &str) -> Result<User, String> {
+    db.find_user_by_name(name)?
+        .ok_or_else(|| format!("There is no user named `{}`!", name))
+}
+
+fn create_user(
+    db: &DatabaseHandler,
+    config: &BreadConfig,
+    name: &str,
+    password: &str,
+    role: Role,
+) -> Result<ObjectId, String> {
+    let id = db.register_user(name, password, &config.argon2)?;
+    if role != Role::User {
+        db.set_user_role(&id, role)?;
+        db.save_audit_entry(&AuditEntry::from_admin_tool(
+            ModerationAction::ChangeRole,
+            id,
+        ))?;
+    }
+
+    Ok(id)
+}
+
+/// Delete a user and everything they have made, and record it in the audit trail.
+/// Returns how many images were deleted.
+fn delete_user(db: &DatabaseHandler, storage: &ImageStorage, name: &str) -> Result<usize, String> {
+    let id = id_of(&find_user(db, name)?)?;
+    let images = db.delete_account(&id)?;
+    db.save_audit_entry(&AuditEntry::from_admin_tool(
+        ModerationAction::DeleteUser,
+        id,
+    ))?;
+
+    // The user is gone either way, so an image that can't be deleted is only reported.
+    let mut deleted = 0;
+    for image in &images {
+        match storage.delete(image) {
+            Ok(()) => deleted += 1,
+            Err(err) => eprintln!("Could not delete image {}: {}", image, err),
+        }
+    }
+
+    Ok(deleted)
+}
+
+fn reset_password(
+    db: &DatabaseHandler,
+    config: &BreadConfig,
+    name: &str,
+    password: &str,
+) -> Result<(), String> {
+    if password.is_empty() {
+        return Err("Password can't be empty!".to_string());
+    }
+    find_user(db, name)?;
+    let hashed_password = User::hash_password(password, &config.argon2)?;
+    db.change_password(name, &hashed_password)?;
+
+    Ok(())
+}
+
+/// Change the role of a user and record it in the audit trail.
+fn set_role(db: &DatabaseHandler, name: &str, role: Role) -> Result<(), String> {
+    let id = id_of(&find_user(db, name)?)?;
+    db.set_user_role(&id, role)?;
+    db.save_audit_entry(&AuditEntry::from_admin_tool(
+        ModerationAction::ChangeRole,
+        id,
+    ))?;
+
+    Ok(())
+}
+
+/// Write the database to a file as canonical extended JSON, which keeps the type of every value.
+fn export(db: &DatabaseHandler, path: &Path) -> Result<(), String> {
+    let export = Bson::Document(db.export()?).into_canonical_extjson();
+    let file = File::create(path).map_err(|err| err.to_string())?;
+    let mut writer = BufWriter::new(file);
+    serde_json::to_writer_pretty(&mut writer, &export).map_err(|err| err.to_string())?;
+
+    writer.flush().map_err(|err| err.to_string())
+}
+
+/// Load a file written by `export`. Returns how many documents were inserted.
+fn import(db: &DatabaseHandler, path: &Path) -> Result<u64, String> {
+    let file = File::open(path).map_err(|err| err.to_string())?;
+    let json: Value =
+        serde_json::from_reader(BufReader::new(file)).map_err(|err| err.to_string())?;
+
+    match Bson::try_from(json).map_err(|err| err.to_string())? {
+        Bson::Document(export) => db.import(export),
+        _ => Err("The file isn't an export of Bread's database!".to_string()),
+    }
+}
+
+fn run_user_command(
+    db: &DatabaseHandler,
+    config: &BreadConfig,
+    command: UserCommand,
+) -> Result<(), AdminError> {
+    match command {
+        UserCommand::Create {
+            name,
+            role,
+            password_stdin,
+        } => {
+            let (password, show_password) = new_password(password_stdin)?;
+            let id = create_user(db, config, &name, &password, role)?;
+            println!("Created user `{}` ({:?}) with id {}.", name, role, id);
+            if show_password {
+                println!("Their password is: {}", password);
+            }
+        }
+        UserCommand::Delete { name } => {
+            let storage = startup::open_image_storage(config)?;
+            let images = delete_user(db, &storage, &name)?;
+            println!(
+                "Deleted user `{}`, their content and {} images.",
+                name, images
+            );
+        }
+        UserCommand::ResetPassword {
+            name,
+            password_stdin,
+        } => {
+            let (password, show_password) = new_password(password_stdin)?;
+            reset_password(db, config, &name, &password)?;
+            println!("Changed the password of `{}`.", name);
+            if show_password {
+                println!("Their new password is: {}", password);
+            }
+        }
+        UserCommand::Promote { name, role } => {
+            set_role(db, &name, role)?;
+            println!("`{}` is now a {:?}.", name, role);
+        }
+    }
+
+    Ok(())
+}
+
+fn run(command: Command) -> Result<(), AdminError> {
+    // Migrating sets itself up, since it is shared with `bread migrate`.
+    if let Command::Migrate = command {
+        return startup::migrate().map_err(AdminError::Startup);
+    }
+
+    let config = startup::load_tool_config()?;
+    let db = startup::connect_database(&config)?;
+
+    match command {
+        Command::User(command) => run_user_command(&db, &config, command)?,
+        Command::Migrate => {}
+        Command::PurgeExpired => {
+            let storage = startup::open_image_storage(&config)?;
+            let count = sweeper::sweep_expired_posts(&db, &storage)?;
+            println!("Deleted {} expired posts.", count);
+        }
+        Command::Export { file } => {
+            export(&db, &file)?;
+            println!(
+                "Exported the database to {}. Copy `{}` to keep the images too.",
+                file.display(),
+                config.image_dir.display()
+            );
+        }
+        Command::Import { file } => {
+            let count = import(&db, &file)?;
+            println!("Imported {} documents from {}.", count, file.display());
+        }
+    }
+
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    match run(Cli::parse().command) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(AdminError::Startup(err)) => {
+            eprintln!("{}", err);
+            err.exit_code()
+        }
+        Err(AdminError::Command(err)) => {
+            eprintln!("{}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::CommandFactory;
+
+    use super::*;
+
+    #[test]
+    fn cli_is_valid() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn roles_default_per_command() {
+        let cli = Cli::try_parse_from(["bread-admin", "user", "create", "baker"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Command::User(UserCommand::Create {
+                role: Role::User,
+                password_stdin: false,
+                ..
+            })
+        ));
+
+        let cli = Cli::try_parse_from(["bread-admin", "user", "promote", "baker"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Command::User(UserCommand::Promote {
+                role: Role::Admin,
+                ..
+            })
+        ));
+
+        let cli = Cli::try_parse_from([
+            "bread-admin",
+            "user",
+            "promote",
+            "baker",
+            "--role",
+            "moderator",
+        ])
+        .unwrap();
+        assert!(matches!(
+            cli.command,
+            Command::User(UserCommand::Promote {
+                role: Role::Moderator,
+                ..
+            })
+        ));
+
+        assert!(Cli::try_parse_from([
+            "bread-admin",
+            "user",
+            "promote",
+            "baker",
+            "--role",
+            "owner"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn generated_passwords_are_random() {
+        let password = generate_password();
+        assert_eq!(password.len(), PASSWORD_LENGTH);
+        assert!(password.bytes().all(|c| PASSWORD_CHARS.contains(&c)));
+        assert_ne!(password, generate_password());
+    }
+
+    /// Needs a database, like the tests in `database`.
+    #[test]
+    fn manage_user() {
+        let config = BreadConfig::default();
+        let db = DatabaseHandler::create_connection(&config.database_name)
+            .expect("Could not connect to the database!");
+        let name = format!("admin-test-{}", ObjectId::new());
+
+        let id = create_user(&db, &config, &name, "first password", Role::Moderator).unwrap();
+        assert_eq!(find_user(&db, &name).unwrap().role, Role::Moderator);
+
+        set_role(&db, &name, Role::Admin).unwrap();
+        assert_eq!(find_user(&db, &name).unwrap().role, Role::Admin);
+        let role_changes = db
+            .find_audit_entries(100)
+            .unwrap()
+            .into_iter()
+            .filter(|entry| entry.target == id && entry.action == ModerationAction::ChangeRole)
+            .inspect(|entry| assert_eq!(entry.moderator, None))
+            .count();
+        assert_eq!(role_changes, 2);
+
+        reset_password(&db, &config, &name, "second password").unwrap();
+        assert!(db.login_user(&name, "first password").is_err());
+        assert!(db.login_user(&name, "second password").is_ok());
+
+        let storage = ImageStorage::new(config.image_dir.clone()).unwrap();
+        assert_eq!(delete_user(&db, &storage, &name).unwrap(), 0);
+        assert!(find_user(&db, &name).is_err());
+        assert!(db
+            .find_audit_entries(100)
+            .unwrap()
+            .iter()
+            .any(|entry| entry.target == id && entry.action == ModerationAction::DeleteUser));
+    }
+}
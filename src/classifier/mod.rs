@@ -0,0 +1,269 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// What a classifier thinks of the content of a post.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Classification {
+    /// Nothing in the content suggests that the author is in crisis.
+    Safe,
+    /// The content suggests that the author is in crisis.
+    /// `language` is the language the content was recognised in, if known.
+    Crisis { language: Option<String> },
+}
+
+/// Something that can look at the content of a post before it is published.
+/// This is a trait so that the keyword list can be replaced by something smarter later on.
+pub trait ContentClassifier: Send + Sync {
+    fn classify(&self, content: &str) -> Classification;
+}
+
+/// A place that people in crisis can turn to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct Helpline {
+    pub name: String,
+    pub phone: Option<String>,
+    pub url: Option<String>,
+}
+
+/// The phrases to look for and the helplines to show for a single language.
+#[derive(Debug, Default, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct LanguageConfig {
+    #[serde(default)]
+    pub phrases: Vec<String>,
+    #[serde(default)]
+    pub helplines: Vec<Helpline>,
+}
+
+/// The `classifier` table in Rocket.toml.
+#[derive(Debug, Default, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct ClassifierConfig {
+    /// Whether posts that trigger the classifier are left out of random discovery.
+    #[serde(default)]
+    pub exclude_from_discovery: bool,
+    /// The language whose helplines are shown when the content's language isn't known.
+    #[serde(default)]
+    pub default_language: String,
+    /// Phrases and helplines, keyed by language code.
+    #[serde(default)]
+    pub languages: HashMap<String, LanguageConfig>,
+}
+
+/// Lower-case a text and replace everything but letters and digits with single spaces.
+/// The result starts and ends with a space, so that phrases only match whole words.
+fn normalize(text: &str) -> String {
+    let mut normalized = String::from(" ");
+
+    for c in text.chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            normalized.push(c);
+        } else if !normalized.ends_with(' ') {
+            normalized.push(' ');
+        }
+    }
+
+    if !normalized.ends_with(' ') {
+        normalized.push(' ');
+    }
+
+    normalized
+}
+
+/// A classifier that looks for a configurable list of phrases in several languages.
+pub struct KeywordClassifier {
+    /// Pairs of language code and normalized phrase, longest phrase first and then by language.
+    phrases: Vec<(String, String)>,
+}
+
+impl KeywordClassifier {
+    /// The phrases are sorted, so that a post matching phrases of several languages always gets the same language.
+    /// Longer phrases go first, since they say more about which language the post is in.
+    pub fn new(languages: &HashMap<String, LanguageConfig>) -> Self {
+        let mut phrases: Vec<(String, String)> = languages
+            .iter()
+            .flat_map(|(language, config)| {
+                config
+                    .phrases
+                    .iter()
+                    .map(|phrase| (language.clone(), normalize(phrase)))
+            })
+            .filter(|(_, phrase)| !phrase.trim().is_empty())
+            .collect();
+        phrases.sort_by(|(language_a, phrase_a), (language_b, phrase_b)| {
+            phrase_b
+                .len()
+                .cmp(&phrase_a.len())
+                .then_with(|| language_a.cmp(language_b))
+                .then_with(|| phrase_a.cmp(phrase_b))
+        });
+
+        KeywordClassifier { phrases }
+    }
+}
+
+impl ContentClassifier for KeywordClassifier {
+    fn classify(&self, content: &str) -> Classification {
+        let content = normalize(content);
+
+        match self
+            .phrases
+            .iter()
+            .find(|(_, phrase)| content.contains(phrase.as_str()))
+        {
+            Some((language, _)) => Classification::Crisis {
+                language: Some(language.clone()),
+            },
+            None => Classification::Safe,
+        }
+    }
+}
+
+/// What should happen to a post that triggered the classifier.
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct CrisisResponse {
+    /// Helplines to show to the author.
+    pub helplines: Vec<Helpline>,
+    /// Whether the post should be left out of random discovery.
+    pub exclude_from_discovery: bool,
+}
+
+/// The classifier that is run on every new post, together with what to do when it is triggered.
+/// This is managed by Rocket.
+pub struct PostClassifier {
+    classifier: Box<dyn ContentClassifier>,
+    config: ClassifierConfig,
+}
+
+impl PostClassifier {
+    /// Create a post classifier that uses a `KeywordClassifier` built from the config.
+    pub fn from_config(config: ClassifierConfig) -> Self {
+        PostClassifier {
+            classifier: Box::new(KeywordClassifier::new(&config.languages)),
+            config,
+        }
+    }
+
+    /// Check the content of a post. Returns `None` if the post can be published as usual.
+    pub fn check(&self, content: &str) -> Option<CrisisResponse> {
+        let language = match self.classifier.classify(content) {
+            Classification::Safe => return None,
+            Classification::Crisis { language } => language,
+        };
+
+        let helplines = language
+            .and_then(|language| self.config.languages.get(&language))
+            .filter(|language| !language.helplines.is_empty())
+            .or_else(|| self.config.languages.get(&self.config.default_language))
+            .map(|language| language.helplines.clone())
+            .unwrap_or_default();
+
+        Some(CrisisResponse {
+            helplines,
+            exclude_from_discovery: self.config.exclude_from_discovery,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_config() -> ClassifierConfig {
+        let mut languages = HashMap::new();
+        languages.insert(
+            "en".to_string(),
+            LanguageConfig {
+                phrases: vec!["want to die".to_string(), "self-harm".to_string()],
+                helplines: vec![Helpline {
+                    name: "English helpline".to_string(),
+                    phone: None,
+                    url: None,
+                }],
+            },
+        );
+        languages.insert(
+            "sv".to_string(),
+            LanguageConfig {
+                phrases: vec!["vill dö".to_string()],
+                helplines: vec![],
+            },
+        );
+
+        ClassifierConfig {
+            exclude_from_discovery: true,
+            default_language: "en".to_string(),
+            languages,
+        }
+    }
+
+    /// Phrases should match regardless of case, punctuation and spacing, but only as whole words.
+    #[test]
+    fn keyword_classifier_matches_phrases() {
+        let classifier = KeywordClassifier::new(&test_config().languages);
+
+        assert_eq!(
+            classifier.classify("Some days I just WANT   to die..."),
+            Classification::Crisis {
+                language: Some("en".to_string())
+            }
+        );
+        assert_eq!(
+            classifier.classify("Thinking about self harm again"),
+            Classification::Crisis {
+                language: Some("en".to_string())
+            }
+        );
+        assert_eq!(
+            classifier.classify("Jag vill dö."),
+            Classification::Crisis {
+                language: Some("sv".to_string())
+            }
+        );
+        assert_eq!(
+            classifier.classify("I want to diet before summer"),
+            Classification::Safe
+        );
+    }
+
+    /// When phrases of several languages match, the longest phrase decides, and then the first language by name.
+    #[test]
+    fn keyword_classifier_picks_languages_deterministically() {
+        let mut languages = HashMap::new();
+        for (language, phrase) in [("sv", "hjälp"), ("da", "hjælp"), ("no", "hjelp mig nu")] {
+            languages.insert(
+                language.to_string(),
+                LanguageConfig {
+                    phrases: vec![phrase.to_string(), "sos".to_string()],
+                    helplines: vec![],
+                },
+            );
+        }
+        let classifier = KeywordClassifier::new(&languages);
+
+        let language = |content| match classifier.classify(content) {
+            Classification::Crisis { language } => language.unwrap(),
+            Classification::Safe => panic!("{} should have matched", content),
+        };
+        assert_eq!(language("sos"), "da");
+        assert_eq!(language("sos hjälp"), "sv");
+        assert_eq!(language("sos hjälp hjelp mig nu"), "no");
+    }
+
+    /// A language without helplines should fall back to the default language's helplines.
+    #[test]
+    fn post_classifier_falls_back_to_default_helplines() {
+        let classifier = PostClassifier::from_config(test_config());
+
+        assert!(classifier.check("Had a lovely walk today!").is_none());
+
+        let response = classifier
+            .check("jag vill dö")
+            .expect("The classifier should have been triggered!");
+        assert!(response.exclude_from_discovery);
+        assert_eq!(response.helplines[0].name, "English helpline");
+    }
+}
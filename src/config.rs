@@ -0,0 +1,254 @@
+use std::path::PathBuf;
+
+use rocket::figment::{providers::Env, Figment, Profile};
+use serde::Deserialize;
+
+use crate::logging::LogFormat;
+
+/// The secret session tokens are signed with when none is configured. Only the debug profile may use it.
+const DEBUG_JWT_SECRET: &str = "SECRET";
+
+/// Shorter secrets are too easy to guess, which would let anyone sign their own session tokens.
+const MIN_JWT_SECRET_LENGTH: usize = 32;
+
+/// Bread's own settings, next to Rocket's in `Rocket.toml`, so that they can differ between profiles.
+/// Each one can be overridden by an environment variable prefixed with `BREAD_`, e.g. `BREAD_POSTS_PER_DAY=3`.
+/// Nested settings are separated by two underscores, e.g. `BREAD_ARGON2__MEM_COST=19456`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(crate = "rocket::serde", default)]
+pub struct BreadConfig {
+    /// The name of the MongoDB database.
+    pub database_name: String,
+    /// How many posts a user can create per day.
+    pub posts_per_day: u32,
+    /// How many random posts a user can see per day.
+    pub random_posts_per_day: u32,
+    /// The secret that session tokens are signed with. Required to serve Bread outside the debug profile.
+    pub jwt_secret: String,
+    pub argon2: Argon2Config,
+    /// Where the images of posts are stored.
+    pub image_dir: PathBuf,
+    /// The language that words in posts are stemmed in when searching, see `DatabaseHandler::create_post_text_index`.
+    pub search_language: String,
+    /// Serve `/metrics` on this port instead of Bread's own, so that it can be kept from the public.
+    pub metrics_port: Option<u16>,
+    /// Whether logs are written as text or as JSON, see `logging::init`.
+    pub log_format: LogFormat,
+    /// Apply pending database migrations when Bread starts. If turned off, Bread refuses to start
+    /// until they have been applied with `bread migrate`.
+    pub migrate_on_startup: bool,
+}
+
+/// How much work hashing a password takes. Changing these only affects passwords hashed afterwards,
+/// since each hash remembers the parameters it was made with.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(crate = "rocket::serde", default)]
+pub struct Argon2Config {
+    /// Memory used per hash, in KiB.
+    pub mem_cost: u32,
+    /// How many passes are made over the memory.
+    pub time_cost: u32,
+    /// How many threads a hash is computed with.
+    pub lanes: u32,
+}
+
+impl Default for BreadConfig {
+    fn default() -> Self {
+        BreadConfig {
+            database_name: "bread".to_string(),
+            posts_per_day: 2,
+            random_posts_per_day: 10,
+            jwt_secret: String::new(),
+            argon2: Argon2Config::default(),
+            image_dir: PathBuf::from("images"),
+            search_language: "english".to_string(),
+            metrics_port: None,
+            log_format: LogFormat::default(),
+            migrate_on_startup: true,
+        }
+    }
+}
+
+impl Default for Argon2Config {
+    fn default() -> Self {
+        Argon2Config {
+            mem_cost: 65536,
+            time_cost: 10,
+            lanes: 4,
+        }
+    }
+}
+
+/// Rocket's configuration, with the `BREAD_` environment variables on top.
+pub fn figment() -> Figment {
+    rocket::Config::figment().merge(Env::prefixed("BREAD_").split("__").global())
+}
+
+impl BreadConfig {
+    /// Read and validate the configuration of the figment's selected profile, for serving Bread.
+    pub fn from_figment(figment: &Figment) -> Result<Self, String> {
+        figment
+            .extract::<BreadConfig>()
+            .map_err(|err| err.to_string())?
+            .validate(figment.profile())
+    }
+
+    /// Read and validate the configuration like `from_figment`, except for `jwt_secret`.
+    /// This is for commands that don't sign session tokens, such as `bread-admin`.
+    pub fn from_figment_without_secret(figment: &Figment) -> Result<Self, String> {
+        let config = figment
+            .extract::<BreadConfig>()
+            .map_err(|err| err.to_string())?;
+        config.validate_settings()?;
+
+        Ok(config)
+    }
+
+    /// Check that the settings make sense, so that mistakes are found at startup instead of on the first request.
+    fn validate(mut self, profile: &Profile) -> Result<Self, String> {
+        self.validate_settings()?;
+
+        if self.jwt_secret.is_empty() {
+            if *profile != rocket::Config::DEBUG_PROFILE {
+                return Err(format!(
+                    "`jwt_secret` must be set in the {} profile, e.g. with BREAD_JWT_SECRET!",
+                    profile
+                ));
+            }
+            warn!("No `jwt_secret` configured, using an insecure one since this is a debug build.");
+            self.jwt_secret = DEBUG_JWT_SECRET.to_string();
+        } else if self.jwt_secret.len() < MIN_JWT_SECRET_LENGTH {
+            return Err(format!(
+                "`jwt_secret` must be at least {} characters long!",
+                MIN_JWT_SECRET_LENGTH
+            ));
+        }
+
+        Ok(self)
+    }
+
+    /// Check every setting but `jwt_secret`.
+    fn validate_settings(&self) -> Result<(), String> {
+        // MongoDB doesn't allow these characters in database names.
+        if self.database_name.is_empty()
+            || self.database_name.len() >= 64
+            || self.database_name.contains(['/', '\\', '.', ' ', '"', '$'])
+        {
+            return Err(format!(
+                "`database_name` \"{}\" isn't a valid MongoDB database name!",
+                self.database_name
+            ));
+        }
+
+        if self.posts_per_day == 0 {
+            return Err("`posts_per_day` must be at least 1!".to_string());
+        }
+        if self.random_posts_per_day == 0 {
+            return Err("`random_posts_per_day` must be at least 1!".to_string());
+        }
+
+        // Argon2 needs at least one pass and eight KiB of memory per lane.
+        let argon2 = &self.argon2;
+        if argon2.time_cost == 0 || argon2.lanes == 0 || argon2.mem_cost < 8 * argon2.lanes {
+            return Err(
+                "`argon2` needs a `time_cost` and `lanes` of at least 1, and a `mem_cost` of at least 8 KiB per lane!"
+                    .to_string(),
+            );
+        }
+
+        if self.search_language.trim().is_empty() {
+            return Err(
+                "`search_language` can't be empty, use \"none\" to turn off stemming!".to_string(),
+            );
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rocket::figment::providers::{Format, Toml};
+
+    use super::*;
+
+    const TOML: &str = r#"
+        [default]
+        posts_per_day = 3
+
+        [default.argon2]
+        mem_cost = 19456
+
+        [release]
+        jwt_secret = "a secret that is long enough to be used"
+    "#;
+
+    /// Settings are read from the selected profile, and the ones that are left out keep their defaults.
+    #[test]
+    fn config_is_read_from_profile() {
+        let figment = Figment::from(Toml::string(TOML).nested()).select("release");
+        let config = BreadConfig::from_figment(&figment).unwrap();
+
+        assert_eq!(config.posts_per_day, 3);
+        assert_eq!(config.random_posts_per_day, 10);
+        assert_eq!(config.argon2.mem_cost, 19456);
+        assert_eq!(config.argon2.time_cost, 10);
+        assert_eq!(config.jwt_secret, "a secret that is long enough to be used");
+    }
+
+    #[test]
+    fn jwt_secret_is_only_optional_in_debug() {
+        let debug = BreadConfig::default().validate(&rocket::Config::DEBUG_PROFILE);
+        assert_eq!(debug.unwrap().jwt_secret, DEBUG_JWT_SECRET);
+
+        assert!(BreadConfig::default()
+            .validate(&rocket::Config::RELEASE_PROFILE)
+            .is_err());
+
+        let short_secret = BreadConfig {
+            jwt_secret: "too short".to_string(),
+            ..Default::default()
+        };
+        assert!(short_secret
+            .validate(&rocket::Config::DEBUG_PROFILE)
+            .is_err());
+    }
+
+    /// Commands that don't sign session tokens can run in release without a secret, but still check the rest.
+    #[test]
+    fn jwt_secret_is_only_needed_to_serve() {
+        let figment = Figment::from(Toml::string(TOML).nested()).select("release");
+        let without_secret = figment.clone().merge(("jwt_secret", ""));
+        assert!(BreadConfig::from_figment(&without_secret).is_err());
+        assert!(BreadConfig::from_figment_without_secret(&without_secret).is_ok());
+
+        let invalid = without_secret.merge(("posts_per_day", 0));
+        assert!(BreadConfig::from_figment_without_secret(&invalid).is_err());
+    }
+
+    #[test]
+    fn invalid_settings_are_rejected() {
+        let invalid = [
+            BreadConfig {
+                database_name: "my.bread".to_string(),
+                ..Default::default()
+            },
+            BreadConfig {
+                posts_per_day: 0,
+                ..Default::default()
+            },
+            BreadConfig {
+                argon2: Argon2Config {
+                    mem_cost: 16,
+                    time_cost: 1,
+                    lanes: 4,
+                },
+                ..Default::default()
+            },
+        ];
+
+        for config in invalid {
+            assert!(config.validate(&rocket::Config::DEBUG_PROFILE).is_err());
+        }
+    }
+}
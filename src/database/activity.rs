@@ -0,0 +1,118 @@
+use mongodb::{
+    bson::{doc, oid::ObjectId, DateTime, Document},
+    options::{FindOptions, UpdateOptions},
+};
+
+use crate::models::activity::{ActivityDay, DailyQuota, SHORT_VISIT_MILLIS, VISIT_GAP_MILLIS};
+
+use super::{err_to_string, DatabaseHandler};
+
+impl DatabaseHandler {
+    /// Note that a user was active on a day.
+    /// If they were last seen less than `VISIT_GAP_MILLIS` ago, the time since then is added to their visit.
+    /// Otherwise a new visit starts, which counts as `SHORT_VISIT_MILLIS`.
+    pub fn record_activity(&self, user: &ObjectId, day: &str) -> Result<(), String> {
+        let now = DateTime::now();
+        let since_last_seen = doc! {
+            "$subtract": [now, { "$ifNull": ["$last_active_at", DateTime::from_millis(0)] }]
+        };
+
+        let options = UpdateOptions::builder().upsert(true).build();
+        self.activity
+            .update_one(
+                doc! { "user": user, "day": day },
+                vec![doc! {
+                    "$set": {
+                        "active_millis": {
+                            "$add": [
+                                { "$ifNull": ["$active_millis", 0_i64] },
+                                {
+                                    "$cond": [
+                                        { "$lte": [since_last_seen.clone(), VISIT_GAP_MILLIS] },
+                                        since_last_seen,
+                                        SHORT_VISIT_MILLIS,
+                                    ]
+                                },
+                            ]
+                        },
+                        "last_active_at": now,
+                    }
+                }],
+                options,
+            )
+            .map_err(err_to_string)?;
+
+        Ok(())
+    }
+
+    /// Get how many minutes a user was active on a day.
+    pub fn count_active_minutes(&self, user: &ObjectId, day: &str) -> Result<usize, String> {
+        Ok(self
+            .activity
+            .find_one(doc! { "user": user, "day": day }, None)
+            .map_err(err_to_string)?
+            .map_or(0, |activity| activity.active_minutes()))
+    }
+
+    /// Get how much of a quota a user has used on a day.
+    pub fn count_quota_used(
+        &self,
+        user: &ObjectId,
+        day: &str,
+        quota: DailyQuota,
+    ) -> Result<u32, String> {
+        Ok(self
+            .activity
+            .find_one(doc! { "user": user, "day": day }, None)
+            .map_err(err_to_string)?
+            .map_or(0, |activity| quota.used(&activity)))
+    }
+
+    /// Use up one of a user's quota for a day, unless they have already used `limit`.
+    /// Returns whether there was any left. Checking and counting happen in one update, so that concurrent requests can't both take the last one.
+    pub fn take_quota(
+        &self,
+        user: &ObjectId,
+        day: &str,
+        quota: DailyQuota,
+        limit: u32,
+    ) -> Result<bool, String> {
+        // The day's document has to exist first. An upsert that didn't match because the quota is used up would insert a second one.
+        let options = UpdateOptions::builder().upsert(true).build();
+        self.activity
+            .update_one(
+                doc! { "user": user, "day": day },
+                doc! { "$setOnInsert": { "last_active_at": DateTime::now() } },
+                options,
+            )
+            .map_err(err_to_string)?;
+
+        // Days from before quotas were counted don't have the field, which `$not` also matches.
+        let mut filter = doc! { "user": user, "day": day };
+        filter.insert(quota.field(), doc! { "$not": { "$gte": i64::from(limit) } });
+        let mut increment = Document::new();
+        increment.insert(quota.field(), 1);
+
+        let result = self
+            .activity
+            .update_one(filter, doc! { "$inc": increment }, None)
+            .map_err(err_to_string)?;
+
+        Ok(result.modified_count == 1)
+    }
+
+    /// Get a user's activity on some days, oldest first. Days without activity are left out.
+    pub fn find_activity(
+        &self,
+        user: &ObjectId,
+        days: &[String],
+    ) -> Result<Vec<ActivityDay>, String> {
+        let options = FindOptions::builder().sort(doc! { "day": 1 }).build();
+
+        self.activity
+            .find(doc! { "user": user, "day": { "$in": days } }, options)
+            .map_err(err_to_string)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(err_to_string)
+    }
+}
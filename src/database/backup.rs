@@ -0,0 +1,78 @@
+use mongodb::bson::{Bson, Document};
+
+use super::{err_to_string, DatabaseHandler};
+
+impl DatabaseHandler {
+    /// Get every document in the database, keyed by the name of its collection.
+    /// Images are stored outside the database, so they aren't included.
+    pub fn export(&self) -> Result<Document, String> {
+        let mut names = self.collection_names()?;
+        names.sort();
+
+        let mut export = Document::new();
+        for name in names {
+            let documents = self
+                .db
+                .collection::<Document>(&name)
+                .find(None, None)
+                .map_err(err_to_string)?
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(err_to_string)?;
+            export.insert(name, documents);
+        }
+
+        Ok(export)
+    }
+
+    /// Insert the documents of an export. Returns how many documents were inserted.
+    /// The collections they are inserted into must be empty, so that nothing is mixed up with or overwrites what is there.
+    pub fn import(&self, export: Document) -> Result<u64, String> {
+        let mut collections = Vec::new();
+        for (name, documents) in export {
+            let documents = match documents {
+                Bson::Array(documents) => documents
+                    .into_iter()
+                    .map(|document| match document {
+                        Bson::Document(document) => Ok(document),
+                        _ => Err(format!("`{}` has something that isn't a document!", name)),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?,
+                _ => return Err(format!("`{}` isn't a list of documents!", name)),
+            };
+
+            let collection = self.db.collection::<Document>(&name);
+            if collection
+                .count_documents(None, None)
+                .map_err(err_to_string)?
+                > 0
+            {
+                return Err(format!("`{}` isn't empty!", name));
+            }
+            collections.push((collection, documents));
+        }
+
+        let mut inserted = 0;
+        for (collection, documents) in collections {
+            if !documents.is_empty() {
+                inserted += collection
+                    .insert_many(documents, None)
+                    .map_err(err_to_string)?
+                    .inserted_ids
+                    .len() as u64;
+            }
+        }
+
+        Ok(inserted)
+    }
+
+    /// The names of Bread's collections, leaving out the ones MongoDB keeps for itself.
+    fn collection_names(&self) -> Result<Vec<String>, String> {
+        Ok(self
+            .db
+            .list_collection_names(None)
+            .map_err(err_to_string)?
+            .into_iter()
+            .filter(|name| !name.starts_with("system."))
+            .collect())
+    }
+}
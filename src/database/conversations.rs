@@ -0,0 +1,131 @@
+use mongodb::{
+    bson::{doc, oid::ObjectId, to_document, DateTime},
+    options::{FindOneAndUpdateOptions, FindOptions, ReturnDocument},
+    results::UpdateResult,
+};
+
+use crate::models::conversation::{Conversation, Message};
+
+use super::{
+    err_to_string,
+    pagination::{Cursor, Page},
+    DatabaseHandler,
+};
+
+impl DatabaseHandler {
+    /// Get the conversation between two users, creating it if it doesn't exist yet.
+    pub fn find_or_create_conversation(
+        &self,
+        a: &ObjectId,
+        b: &ObjectId,
+    ) -> Result<Conversation, String> {
+        let conversation = Conversation::create(*a, *b);
+        let options = FindOneAndUpdateOptions::builder()
+            .upsert(true)
+            .return_document(ReturnDocument::After)
+            .build();
+
+        self.conversations
+            .find_one_and_update(
+                doc! { "first": conversation.first, "second": conversation.second },
+                doc! { "$setOnInsert": to_document(&conversation).map_err(err_to_string)? },
+                options,
+            )
+            .map_err(err_to_string)?
+            .ok_or_else(|| "Couldn't create the conversation!".to_string())
+    }
+
+    /// Get a conversation from the database via its id.
+    pub fn find_conversation_by_id(&self, id: &ObjectId) -> Result<Option<Conversation>, String> {
+        self.conversations
+            .find_one(doc! { "_id": id }, None)
+            .map_err(err_to_string)
+    }
+
+    /// Get all conversations a user takes part in, the most recently active first.
+    pub fn find_conversations_of(&self, user: &ObjectId) -> Result<Vec<Conversation>, String> {
+        let options = FindOptions::builder()
+            .sort(doc! { "last_message_at": -1, "created_at": -1 })
+            .build();
+
+        self.conversations
+            .find(
+                doc! { "$or": [{ "first": user }, { "second": user }] },
+                options,
+            )
+            .map_err(err_to_string)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(err_to_string)
+    }
+
+    /// Remember that a user has read a conversation up until now.
+    pub fn mark_conversation_read(
+        &self,
+        conversation: &Conversation,
+        user: &ObjectId,
+    ) -> Result<UpdateResult, String> {
+        self.conversations
+            .update_one(
+                doc! { "_id": conversation.id },
+                doc! { "$set": { conversation.read_at_field(user): DateTime::now() } },
+                None,
+            )
+            .map_err(err_to_string)
+    }
+
+    /// Saves a message to the database and moves its conversation to the top of the inbox.
+    /// Returns the id of the created message as an option.
+    pub fn save_message(&self, message: &Message) -> Result<Option<ObjectId>, String> {
+        let result = self
+            .messages
+            .insert_one(message, None)
+            .map_err(err_to_string)?;
+
+        self.conversations
+            .update_one(
+                doc! { "_id": message.conversation },
+                doc! { "$set": {
+                    "last_message_at": message.created_at,
+                    "last_sender": message.sender,
+                } },
+                None,
+            )
+            .map_err(err_to_string)?;
+
+        Ok(result.inserted_id.as_object_id())
+    }
+
+    /// Get a page of the messages in a conversation, newest first.
+    /// `cursor` is the `next_cursor` of the previous page, or `None` to get the first page.
+    pub fn find_messages(
+        &self,
+        conversation: &ObjectId,
+        cursor: Option<&str>,
+        limit: i64,
+    ) -> Result<Page<Message>, String> {
+        let mut filter = doc! { "conversation": conversation };
+        if let Some(cursor) = cursor {
+            filter.extend(Cursor::decode(cursor)?.filter());
+        }
+
+        // Fetch one message more than asked for, to know if there is another page.
+        let options = FindOptions::builder()
+            .sort(doc! { "created_at": -1, "_id": -1 })
+            .limit(limit + 1)
+            .build();
+
+        let messages = self
+            .messages
+            .find(filter, options)
+            .map_err(err_to_string)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(err_to_string)?;
+
+        Ok(Page::from_overfetched(messages, limit, |message| {
+            message.id.map(|id| Cursor {
+                created_at: message.created_at,
+                id,
+            })
+        }))
+    }
+}
@@ -0,0 +1,33 @@
+use mongodb::{
+    bson::{doc, oid::ObjectId, to_bson},
+    results::UpdateResult,
+};
+
+use crate::models::user::Device;
+
+use super::{err_to_string, DatabaseHandler};
+
+impl DatabaseHandler {
+    /// Add a device to a user, replacing any earlier device with the same id.
+    pub fn save_device(&self, user: &ObjectId, device: &Device) -> Result<UpdateResult, String> {
+        self.delete_device(user, &device.id)?;
+        self.users
+            .update_one(
+                doc! { "_id": user },
+                doc! { "$push": { "devices": to_bson(device).map_err(err_to_string)? } },
+                None,
+            )
+            .map_err(err_to_string)
+    }
+
+    /// Remove a device from a user.
+    pub fn delete_device(&self, user: &ObjectId, device_id: &str) -> Result<UpdateResult, String> {
+        self.users
+            .update_one(
+                doc! { "_id": user },
+                doc! { "$pull": { "devices": { "id": device_id } } },
+                None,
+            )
+            .map_err(err_to_string)
+    }
+}
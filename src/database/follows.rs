@@ -0,0 +1,151 @@
+use mongodb::{
+    bson::{doc, oid::ObjectId, to_bson, to_document, Bson},
+    options::UpdateOptions,
+    results::{DeleteResult, UpdateResult},
+};
+
+use crate::models::follow::{Follow, FollowStatus};
+
+use super::{err_to_string, DatabaseHandler};
+
+/// The stored form of a follow status, for use in filters.
+fn status(status: FollowStatus) -> Result<Bson, String> {
+    to_bson(&status).map_err(err_to_string)
+}
+
+impl DatabaseHandler {
+    /// Saves a follow to the database. Following someone twice does nothing.
+    pub fn save_follow(&self, follow: &Follow) -> Result<(), String> {
+        let options = UpdateOptions::builder().upsert(true).build();
+        self.follows
+            .update_one(
+                doc! { "follower": follow.follower, "followee": follow.followee },
+                doc! { "$setOnInsert": to_document(follow).map_err(err_to_string)? },
+                options,
+            )
+            .map_err(err_to_string)?;
+
+        Ok(())
+    }
+
+    /// Delete a follow or follow request from the database.
+    pub fn delete_follow(
+        &self,
+        follower: &ObjectId,
+        followee: &ObjectId,
+    ) -> Result<DeleteResult, String> {
+        self.follows
+            .delete_one(doc! { "follower": follower, "followee": followee }, None)
+            .map_err(err_to_string)
+    }
+
+    /// Get the follow or follow request from one user to another, if there is one.
+    pub fn find_follow(
+        &self,
+        follower: &ObjectId,
+        followee: &ObjectId,
+    ) -> Result<Option<Follow>, String> {
+        self.follows
+            .find_one(doc! { "follower": follower, "followee": followee }, None)
+            .map_err(err_to_string)
+    }
+
+    /// Check if a user follows another user. Follow requests that haven't been accepted don't count.
+    pub fn is_following(&self, follower: &ObjectId, followee: &ObjectId) -> Result<bool, String> {
+        self.follows
+            .count_documents(
+                doc! {
+                    "follower": follower,
+                    "followee": followee,
+                    "status": { "$ne": status(FollowStatus::Pending)? },
+                },
+                None,
+            )
+            .map(|count| count > 0)
+            .map_err(err_to_string)
+    }
+
+    /// Get the ids of all users that a user follows.
+    pub fn find_followees(&self, follower: &ObjectId) -> Result<Vec<ObjectId>, String> {
+        self.follows
+            .find(
+                doc! { "follower": follower, "status": { "$ne": status(FollowStatus::Pending)? } },
+                None,
+            )
+            .map_err(err_to_string)?
+            .map(|follow| follow.map(|follow| follow.followee).map_err(err_to_string))
+            .collect()
+    }
+
+    /// Get the ids of all users that follow a user.
+    pub fn find_followers(&self, followee: &ObjectId) -> Result<Vec<ObjectId>, String> {
+        self.follows
+            .find(
+                doc! { "followee": followee, "status": { "$ne": status(FollowStatus::Pending)? } },
+                None,
+            )
+            .map_err(err_to_string)?
+            .map(|follow| follow.map(|follow| follow.follower).map_err(err_to_string))
+            .collect()
+    }
+
+    /// Get the ids of all users that have asked to follow a user.
+    pub fn find_follow_requests(&self, followee: &ObjectId) -> Result<Vec<ObjectId>, String> {
+        self.follows
+            .find(
+                doc! { "followee": followee, "status": status(FollowStatus::Pending)? },
+                None,
+            )
+            .map_err(err_to_string)?
+            .map(|follow| follow.map(|follow| follow.follower).map_err(err_to_string))
+            .collect()
+    }
+
+    /// Accept a follow request. Follows that were already accepted are left alone.
+    pub fn accept_follow_request(
+        &self,
+        follower: &ObjectId,
+        followee: &ObjectId,
+    ) -> Result<UpdateResult, String> {
+        self.follows
+            .update_one(
+                doc! {
+                    "follower": follower,
+                    "followee": followee,
+                    "status": status(FollowStatus::Pending)?,
+                },
+                doc! { "$set": { "status": status(FollowStatus::Accepted)? } },
+                None,
+            )
+            .map_err(err_to_string)
+    }
+
+    /// Accept all of a user's follow requests, e.g. because they made their account public.
+    pub fn accept_all_follow_requests(&self, followee: &ObjectId) -> Result<UpdateResult, String> {
+        self.follows
+            .update_many(
+                doc! { "followee": followee, "status": status(FollowStatus::Pending)? },
+                doc! { "$set": { "status": status(FollowStatus::Accepted)? } },
+                None,
+            )
+            .map_err(err_to_string)
+    }
+
+    /// Decline a follow request. Follows that were already accepted are left alone.
+    pub fn decline_follow_request(
+        &self,
+        follower: &ObjectId,
+        followee: &ObjectId,
+    ) -> Result<DeleteResult, String> {
+        self.follows
+            .delete_one(
+                doc! {
+                    "follower": follower,
+                    "followee": followee,
+                    "status": status(FollowStatus::Pending)?,
+                },
+                None,
+            )
+            .map_err(err_to_string)
+    }
+}
@@ -0,0 +1,237 @@
+use mongodb::{
+    bson::{doc, to_bson, Bson, DateTime, Document},
+    options::UpdateOptions,
+    sync::Collection,
+};
+
+use crate::models::{
+    post::parse_topics,
+    user::{Role, WellbeingPreferences},
+};
+
+use super::{err_to_string, DatabaseHandler};
+
+/// A change to the documents in the database, for when the models change.
+/// A migration must be safe to run again, since Bread could stop after running it but before recording it.
+pub struct Migration {
+    /// Migrations are applied in order of their versions, which are never reused.
+    pub version: i32,
+    pub name: &'static str,
+    /// Returns how many documents were changed.
+    run: fn(&DatabaseHandler) -> Result<u64, String>,
+}
+
+/// All migrations, oldest first. New ones go at the end.
+const MIGRATIONS: [Migration; 5] = [
+    Migration {
+        version: 1,
+        name: "posts-created-at",
+        run: DatabaseHandler::backfill_post_created_at,
+    },
+    Migration {
+        version: 2,
+        name: "post-defaults",
+        run: DatabaseHandler::backfill_post_defaults,
+    },
+    Migration {
+        version: 3,
+        name: "post-topics",
+        run: DatabaseHandler::backfill_post_topics,
+    },
+    Migration {
+        version: 4,
+        name: "user-defaults",
+        run: DatabaseHandler::backfill_user_defaults,
+    },
+    Migration {
+        version: 5,
+        name: "post-author-private",
+        run: DatabaseHandler::backfill_post_author_private,
+    },
+];
+
+/// Set fields to a default value in the documents that don't have them.
+fn set_missing<T>(collection: &Collection<T>, defaults: Document) -> Result<u64, String> {
+    let mut changed = 0;
+    for (field, value) in defaults {
+        let mut filter = Document::new();
+        filter.insert(field.clone(), doc! { "$exists": false });
+        let mut update = Document::new();
+        update.insert(field, value);
+
+        changed += collection
+            .update_many(filter, doc! { "$set": update }, None)
+            .map_err(err_to_string)?
+            .modified_count;
+    }
+
+    Ok(changed)
+}
+
+impl DatabaseHandler {
+    /// Get the migrations that haven't been applied yet, oldest first.
+    /// Fails if the database has a migration this version of Bread doesn't know of,
+    /// since it was then migrated by a newer version, whose documents this one may not understand.
+    pub fn pending_migrations(&self) -> Result<Vec<&'static Migration>, String> {
+        let applied = self
+            .migrations
+            .find(None, None)
+            .map_err(err_to_string)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(err_to_string)?;
+
+        if let Some(unknown) = applied.iter().find(|applied| {
+            !MIGRATIONS
+                .iter()
+                .any(|migration| migration.version == applied.version)
+        }) {
+            return Err(format!(
+                "The database has migration {} ({}), which this version of Bread doesn't know of!",
+                unknown.version, unknown.name
+            ));
+        }
+
+        Ok(MIGRATIONS
+            .iter()
+            .filter(|migration| {
+                !applied
+                    .iter()
+                    .any(|applied| applied.version == migration.version)
+            })
+            .collect())
+    }
+
+    /// Apply the migrations that haven't been applied yet, oldest first, and get the ones that were applied.
+    /// Stops at the first one that fails, so that later migrations can rely on the earlier ones.
+    pub fn run_migrations(&self) -> Result<Vec<&'static Migration>, String> {
+        let pending = self.pending_migrations()?;
+
+        for migration in &pending {
+            let changed = (migration.run)(self).map_err(|err| {
+                format!(
+                    "Migration {} ({}) failed: {}",
+                    migration.version, migration.name, err
+                )
+            })?;
+            self.record_migration(migration)?;
+            info!(
+                "Applied migration {} ({}), {} documents changed.",
+                migration.version, migration.name, changed
+            );
+        }
+
+        Ok(pending)
+    }
+
+    /// Note that a migration has been applied. If another Bread has already noted it, its note is kept.
+    fn record_migration(&self, migration: &Migration) -> Result<(), String> {
+        let options = UpdateOptions::builder().upsert(true).build();
+        self.migrations
+            .update_one(
+                doc! { "_id": migration.version },
+                doc! {
+                    "$setOnInsert": {
+                        "name": migration.name,
+                        "applied_at": DateTime::now(),
+                    }
+                },
+                options,
+            )
+            .map_err(err_to_string)?;
+
+        Ok(())
+    }
+
+    /// Posts saved before posts had timestamps get the time their ID was created, which is when they were saved.
+    fn backfill_post_created_at(&self) -> Result<u64, String> {
+        self.posts
+            .update_many(
+                doc! { "created_at": { "$exists": false } },
+                vec![doc! { "$set": { "created_at": { "$toDate": "$_id" } } }],
+                None,
+            )
+            .map(|result| result.modified_count)
+            .map_err(err_to_string)
+    }
+
+    fn backfill_post_defaults(&self) -> Result<u64, String> {
+        set_missing(
+            &self.posts,
+            doc! {
+                "edited_at": Bson::Null,
+                "expires_at": Bson::Null,
+                "hidden": false,
+                "excluded_from_discovery": false,
+                "content_warning": Bson::Null,
+            },
+        )
+    }
+
+    /// Posts saved before posts had topics get the topics of the hashtags in their content.
+    fn backfill_post_topics(&self) -> Result<u64, String> {
+        let posts = self.posts.clone_with_type::<Document>();
+        let without_topics = posts
+            .find(doc! { "topics": { "$exists": false } }, None)
+            .map_err(err_to_string)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(err_to_string)?;
+
+        let mut changed = 0;
+        for post in without_topics {
+            let topics = post
+                .get_str("content")
+                .map(parse_topics)
+                .unwrap_or_default();
+            changed += posts
+                .update_one(
+                    doc! { "_id": post.get("_id"), "topics": { "$exists": false } },
+                    doc! { "$set": { "topics": topics } },
+                    None,
+                )
+                .map_err(err_to_string)?
+                .modified_count;
+        }
+
+        Ok(changed)
+    }
+
+    fn backfill_user_defaults(&self) -> Result<u64, String> {
+        set_missing(
+            &self.users,
+            doc! {
+                "role": to_bson(&Role::default()).map_err(err_to_string)?,
+                "suspended": false,
+                "devices": [],
+                "preferences.send_read_receipts": true,
+                "preferences.is_private": false,
+                "preferences.topics": [],
+                "preferences.expanded_warnings": [],
+                "preferences.hidden_warnings": [],
+                "preferences.wellbeing": to_bson(&WellbeingPreferences::default()).map_err(err_to_string)?,
+            },
+        )
+    }
+
+    /// Posts get a copy of whether their author's account is private.
+    fn backfill_post_author_private(&self) -> Result<u64, String> {
+        let mut changed = set_missing(&self.posts, doc! { "author_private": false })?;
+        for author in self.find_private_users()? {
+            changed += self.set_posts_author_private(&author, true)?.modified_count;
+        }
+
+        Ok(changed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Versions decide the order migrations are applied in, and which have been, so they must go up one by one.
+    #[test]
+    fn migration_versions_follow_each_other() {
+        for (i, migration) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(migration.version, i as i32 + 1, "{}", migration.name);
+        }
+    }
+}
@@ -1,18 +1,80 @@
 use dotenv::dotenv;
 use mongodb::{
-    bson::{doc, oid::ObjectId},
+    bson::{doc, oid::ObjectId, to_bson, DateTime, Document},
+    options::{ClientOptions, FindOptions, IndexOptions},
     results::{DeleteResult, UpdateResult},
-    sync::{Client, Collection},
+    sync::{Client, Collection, Database},
+    IndexModel,
 };
-use std::{env, fmt::Display};
+use std::{env, fmt::Display, thread, time::Duration};
+
+use crate::{
+    config::Argon2Config,
+    models::{
+        activity::ActivityDay,
+        audit::AuditEntry,
+        conversation::{Conversation, Message},
+        follow::Follow,
+        migration::AppliedMigration,
+        post::{parse_topics, ContentWarning, Post},
+        reaction::Reaction,
+        reply::{HourlyReplies, Reply},
+        report::Report,
+        restriction::Restriction,
+        user::{User, UserPreferences},
+    },
+};
+
+use self::pagination::{Cursor, Page};
+
+mod activity;
+mod backup;
+mod conversations;
+mod devices;
+mod follows;
+pub mod migrations;
+mod moderation;
+pub mod pagination;
+mod reactions;
+mod replies;
+mod reports;
+mod restrictions;
+pub mod search;
 
-use crate::models::{post::Post, user::User};
+/// How many times to try reaching the database at startup before giving up.
+const CONNECT_ATTEMPTS: u32 = 5;
+
+/// How long to wait after the first failed attempt to reach the database. The wait doubles after each attempt.
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// How long an operation waits for the database to become reachable before failing.
+/// MongoDB's default of 30 seconds would make a missing database look like a hanging server.
+const SERVER_SELECTION_TIMEOUT: Duration = Duration::from_secs(5);
 
 /// This holds a database and makes shortcuts for the respective collections.
+/// Cloning it is cheap, the clones share the same connection.
+#[derive(Clone)]
 pub struct DatabaseHandler {
-    // db: Database,
+    db: Database,
     users: Collection<User>,
     posts: Collection<Post>,
+    audit_log: Collection<AuditEntry>,
+    reports: Collection<Report>,
+    follows: Collection<Follow>,
+    reactions: Collection<Reaction>,
+    replies: Collection<Reply>,
+    hourly_replies: Collection<HourlyReplies>,
+    conversations: Collection<Conversation>,
+    messages: Collection<Message>,
+    restrictions: Collection<Restriction>,
+    activity: Collection<ActivityDay>,
+    migrations: Collection<AppliedMigration>,
+}
+
+/// A filter that matches posts that haven't expired yet.
+/// Expired posts may still be in the database until they have been swept.
+fn not_expired() -> Document {
+    doc! { "expires_at": { "$not": { "$lte": DateTime::now() } } }
 }
 
 /// This function converts an error to a string so that errors easily can be used in a Result<T, String> no matter what caused the error.
@@ -20,17 +82,294 @@ fn err_to_string<E: Display>(err: E) -> String {
     err.to_string()
 }
 
+/// Check that a database can be reached.
+fn ping(db: &Database) -> Result<(), String> {
+    db.run_command(doc! { "ping": 1 }, None)
+        .map(|_| ())
+        .map_err(err_to_string)
+}
+
+/// Check that the database can be reached. MongoDB may still be starting, e.g. when both are started
+/// together, so this tries `CONNECT_ATTEMPTS` times, waiting twice as long after each failure.
+fn ping_with_retries(db: &Database) -> Result<(), String> {
+    let mut delay = FIRST_RETRY_DELAY;
+    for attempt in 1..=CONNECT_ATTEMPTS {
+        match ping(db) {
+            Ok(_) => return Ok(()),
+            Err(err) if attempt < CONNECT_ATTEMPTS => {
+                warn!(
+                    "Could not reach the database (attempt {} of {}), trying again in {} s: {}",
+                    attempt,
+                    CONNECT_ATTEMPTS,
+                    delay.as_secs(),
+                    err
+                );
+                thread::sleep(delay);
+                delay *= 2;
+            }
+            Err(err) => {
+                return Err(format!(
+                    "Gave up after {} attempts: {}",
+                    CONNECT_ATTEMPTS, err
+                ))
+            }
+        }
+    }
+
+    Ok(())
+}
+
 impl DatabaseHandler {
-    /// Create a connection to a database.
-    /// This requires a file called ".env" with the environment variable "MONGO_URI" in it.
-    pub fn create_connection() -> Result<Self, String> {
-        dotenv().map_err(err_to_string)?;
-        let uri = env::var("MONGO_URI").map_err(err_to_string)?;
-        let client = Client::with_uri_str(uri).map_err(err_to_string)?;
-        let db = client.database("bread");
+    /// Create a connection to the database with the given name, and make sure the database can be reached.
+    /// The server is read from the environment variable "MONGO_URI", which may also be set in a file called ".env".
+    pub fn create_connection(database_name: &str) -> Result<Self, String> {
+        match dotenv() {
+            Ok(_) => {}
+            Err(err) if err.not_found() => {}
+            Err(err) => return Err(format!("Could not read .env: {}", err)),
+        }
+        let uri = env::var("MONGO_URI")
+            .map_err(|_| "MONGO_URI isn't set, neither in the environment nor in .env!")?;
+        let mut options = ClientOptions::parse(uri).map_err(err_to_string)?;
+        options.server_selection_timeout = Some(SERVER_SELECTION_TIMEOUT);
+        let client = Client::with_options(options).map_err(err_to_string)?;
+        let db = client.database(database_name);
+        ping_with_retries(&db)?;
         let users = db.collection::<User>("users");
         let posts = db.collection::<Post>("posts");
-        Ok(Self { users, posts })
+        let audit_log = db.collection::<AuditEntry>("audit_log");
+        let reports = db.collection::<Report>("reports");
+        let follows = db.collection::<Follow>("follows");
+        let reactions = db.collection::<Reaction>("reactions");
+        let replies = db.collection::<Reply>("replies");
+        let hourly_replies = db.collection::<HourlyReplies>("hourly_replies");
+        let conversations = db.collection::<Conversation>("conversations");
+        let messages = db.collection::<Message>("messages");
+        let restrictions = db.collection::<Restriction>("restrictions");
+        let activity = db.collection::<ActivityDay>("activity");
+        let migrations = db.collection::<AppliedMigration>("migrations");
+        let handler = Self {
+            db,
+            users,
+            posts,
+            audit_log,
+            reports,
+            follows,
+            reactions,
+            replies,
+            hourly_replies,
+            conversations,
+            messages,
+            restrictions,
+            activity,
+            migrations,
+        };
+        handler.create_indexes()?;
+        Ok(handler)
+    }
+
+    /// Check that the database can be reached right now.
+    pub fn ping(&self) -> Result<(), String> {
+        ping(&self.db)
+    }
+
+    /// Make sure the indexes that Bread relies on exist.
+    /// Creating an index that already exists does nothing.
+    fn create_indexes(&self) -> Result<(), String> {
+        self.create_report_index()?;
+
+        // Each user may only follow another user once.
+        self.follows
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "follower": 1, "followee": 1 })
+                    .options(IndexOptions::builder().unique(true).build())
+                    .build(),
+                None,
+            )
+            .map_err(err_to_string)?;
+
+        // Each user may only have one reaction per post.
+        self.reactions
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "post": 1, "reactor": 1 })
+                    .options(IndexOptions::builder().unique(true).build())
+                    .build(),
+                None,
+            )
+            .map_err(err_to_string)?;
+
+        // Replies are listed per recipient, newest first.
+        self.replies
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "to": 1, "created_at": -1, "_id": -1 })
+                    .build(),
+                None,
+            )
+            .map_err(err_to_string)?;
+
+        // Each user has one count of replies per hour, which is forgotten once the hour is long over.
+        self.hourly_replies
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "from": 1, "hour": 1 })
+                    .options(IndexOptions::builder().unique(true).build())
+                    .build(),
+                None,
+            )
+            .map_err(err_to_string)?;
+        self.hourly_replies
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "hour": 1 })
+                    .options(
+                        IndexOptions::builder()
+                            .expire_after(Duration::from_secs(2 * 60 * 60))
+                            .build(),
+                    )
+                    .build(),
+                None,
+            )
+            .map_err(err_to_string)?;
+
+        // Each pair of users has only one conversation, and users look up the conversations they take part in.
+        self.conversations
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "first": 1, "second": 1 })
+                    .options(IndexOptions::builder().unique(true).build())
+                    .build(),
+                None,
+            )
+            .map_err(err_to_string)?;
+        self.conversations
+            .create_index(
+                IndexModel::builder().keys(doc! { "second": 1 }).build(),
+                None,
+            )
+            .map_err(err_to_string)?;
+
+        // Messages are listed per conversation, newest first.
+        self.messages
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "conversation": 1, "created_at": -1, "_id": -1 })
+                    .build(),
+                None,
+            )
+            .map_err(err_to_string)?;
+
+        // A user can only block or mute someone once, and blocks are also looked up by whom they target.
+        self.restrictions
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "user": 1, "target": 1, "kind": 1 })
+                    .options(IndexOptions::builder().unique(true).build())
+                    .build(),
+                None,
+            )
+            .map_err(err_to_string)?;
+        self.restrictions
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "target": 1, "kind": 1 })
+                    .build(),
+                None,
+            )
+            .map_err(err_to_string)?;
+
+        // Each user has one activity document per day, and activity is forgotten after about a month.
+        self.activity
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "user": 1, "day": 1 })
+                    .options(IndexOptions::builder().unique(true).build())
+                    .build(),
+                None,
+            )
+            .map_err(err_to_string)?;
+        self.activity
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "last_active_at": 1 })
+                    .options(
+                        IndexOptions::builder()
+                            .expire_after(Duration::from_secs(35 * 24 * 60 * 60))
+                            .build(),
+                    )
+                    .build(),
+                None,
+            )
+            .map_err(err_to_string)?;
+
+        // Users are searched by name without regard to case.
+        self.users
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "name": 1 })
+                    .options(
+                        IndexOptions::builder()
+                            .collation(search::name_collation())
+                            .build(),
+                    )
+                    .build(),
+                None,
+            )
+            .map_err(err_to_string)?;
+
+        // Private accounts are left out of random discovery.
+        self.users
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "preferences.is_private": 1 })
+                    .build(),
+                None,
+            )
+            .map_err(err_to_string)?;
+
+        // Expired posts are looked up by the sweeper.
+        self.posts
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "expires_at": 1 })
+                    .options(IndexOptions::builder().sparse(true).build())
+                    .build(),
+                None,
+            )
+            .map_err(err_to_string)?;
+
+        // Random discovery can be narrowed down to topics.
+        self.posts
+            .create_index(
+                IndexModel::builder().keys(doc! { "topics": 1 }).build(),
+                None,
+            )
+            .map_err(err_to_string)?;
+
+        // Images are only served after looking up the post they belong to.
+        self.posts
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "image": 1 })
+                    .options(IndexOptions::builder().sparse(true).build())
+                    .build(),
+                None,
+            )
+            .map_err(err_to_string)?;
+
+        // Posts are listed per author, newest first.
+        self.posts
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "author": 1, "created_at": -1, "_id": -1 })
+                    .build(),
+                None,
+            )
+            .map_err(err_to_string)?;
+
+        Ok(())
     }
 
     /*
@@ -58,6 +397,44 @@ impl DatabaseHandler {
             .map_err(err_to_string)
     }
 
+    /// Get several users from the database via their ids, sorted by name.
+    pub fn find_users_by_ids(&self, ids: &[ObjectId]) -> Result<Vec<User>, String> {
+        let options = FindOptions::builder().sort(doc! { "name": 1 }).build();
+
+        self.users
+            .find(doc! { "_id": { "$in": ids } }, options)
+            .map_err(err_to_string)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(err_to_string)
+    }
+
+    /// Get the ids of all users with private accounts.
+    pub fn find_private_users(&self) -> Result<Vec<ObjectId>, String> {
+        self.users
+            .find(doc! { "preferences.is_private": true }, None)
+            .map_err(err_to_string)?
+            .map(|user| {
+                user.map_err(err_to_string)
+                    .and_then(|user| user.id.ok_or_else(|| "User without id!".to_string()))
+            })
+            .collect()
+    }
+
+    /// Note on all of an author's posts whether their account is private.
+    pub fn set_posts_author_private(
+        &self,
+        author: &ObjectId,
+        private: bool,
+    ) -> Result<UpdateResult, String> {
+        self.posts
+            .update_many(
+                doc! { "author": author },
+                doc! { "$set": { "author_private": private } },
+                None,
+            )
+            .map_err(err_to_string)
+    }
+
     /// Delete a user from the database via its id.
     pub fn delete_user(&self, id: &ObjectId) -> Result<DeleteResult, String> {
         self.users
@@ -65,6 +442,75 @@ impl DatabaseHandler {
             .map_err(err_to_string)
     }
 
+    /// Delete a user along with everything they have made: their posts and the reactions and replies to them,
+    /// their own reactions, replies, follows, conversations, restrictions, reports and activity.
+    /// Returns the images of the deleted posts, which are left for the caller to delete from the image storage.
+    pub fn delete_account(&self, id: &ObjectId) -> Result<Vec<String>, String> {
+        let posts = self
+            .posts
+            .find(doc! { "author": id }, None)
+            .map_err(err_to_string)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(err_to_string)?;
+        let post_ids: Vec<ObjectId> = posts.iter().filter_map(|post| post.id).collect();
+        let conversation_ids: Vec<ObjectId> = self
+            .conversations
+            .find(doc! { "$or": [{ "first": id }, { "second": id }] }, None)
+            .map_err(err_to_string)?
+            .map(|conversation| conversation.map(|conversation| conversation.id))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(err_to_string)?
+            .into_iter()
+            .flatten()
+            .collect();
+
+        self.reactions
+            .delete_many(
+                doc! { "$or": [{ "reactor": id }, { "post": { "$in": &post_ids } }] },
+                None,
+            )
+            .map_err(err_to_string)?;
+        self.replies
+            .delete_many(
+                doc! { "$or": [{ "from": id }, { "to": id }, { "post": { "$in": &post_ids } }] },
+                None,
+            )
+            .map_err(err_to_string)?;
+        self.hourly_replies
+            .delete_many(doc! { "from": id }, None)
+            .map_err(err_to_string)?;
+        self.reports
+            .delete_many(
+                doc! { "$or": [{ "reporter": id }, { "post": { "$in": &post_ids } }] },
+                None,
+            )
+            .map_err(err_to_string)?;
+        self.messages
+            .delete_many(doc! { "conversation": { "$in": &conversation_ids } }, None)
+            .map_err(err_to_string)?;
+        self.conversations
+            .delete_many(doc! { "_id": { "$in": &conversation_ids } }, None)
+            .map_err(err_to_string)?;
+        self.follows
+            .delete_many(
+                doc! { "$or": [{ "follower": id }, { "followee": id }] },
+                None,
+            )
+            .map_err(err_to_string)?;
+        self.restrictions
+            .delete_many(doc! { "$or": [{ "user": id }, { "target": id }] }, None)
+            .map_err(err_to_string)?;
+        self.activity
+            .delete_many(doc! { "user": id }, None)
+            .map_err(err_to_string)?;
+        self.posts
+            .delete_many(doc! { "author": id }, None)
+            .map_err(err_to_string)?;
+        self.delete_user(id)?;
+
+        Ok(posts.into_iter().filter_map(|post| post.image).collect())
+    }
+
     /// Change a password for a user.
     /// The `new_password` parameter should be hashed!
     pub fn change_password(
@@ -75,12 +521,70 @@ impl DatabaseHandler {
         self.users
             .update_one(
                 doc! { "name": username },
-                doc! { "password": new_password },
+                doc! { "$set": { "password": new_password } },
                 None,
             )
             .map_err(err_to_string)
     }
 
+    /// Change a user's name and preferences, so long as no other user has that name.
+    pub fn update_user_settings(
+        &self,
+        id: &ObjectId,
+        name: &str,
+        preferences: &UserPreferences,
+    ) -> Result<UpdateResult, String> {
+        if name.trim().is_empty() {
+            return Err("Username can't be empty!".to_string());
+        }
+
+        if self
+            .find_user_by_name(name)?
+            .is_some_and(|user| user.id.as_ref() != Some(id))
+        {
+            return Err("User already exists with that name!".to_string());
+        }
+
+        self.users
+            .update_one(
+                doc! { "_id": id },
+                doc! { "$set": {
+                    "name": name,
+                    "preferences": to_bson(preferences).map_err(err_to_string)?,
+                } },
+                None,
+            )
+            .map_err(err_to_string)
+    }
+
+    /// Create and save a new user, so long as no user with that name already exists.
+    /// The password is hashed with the given parameters. Returns the id of the created user.
+    pub fn register_user(
+        &self,
+        username: &str,
+        password: &str,
+        argon2: &Argon2Config,
+    ) -> Result<ObjectId, String> {
+        if username.trim().is_empty() {
+            return Err("Username can't be empty!".to_string());
+        }
+
+        if password.is_empty() {
+            return Err("Password can't be empty!".to_string());
+        }
+
+        if self.find_user_by_name(username)?.is_some() {
+            return Err("User already exists with that name!".to_string());
+        }
+
+        let user = User::create(username.to_owned(), password.to_owned(), argon2)?;
+
+        match self.save_user(&user)? {
+            Some(id) => Ok(id),
+            None => Err("Couldn't get ObjectId of the user!".to_string()),
+        }
+    }
+
     /// Check if a password matches a user's password.
     pub fn login_user(&self, username: &str, password: &str) -> Result<User, String> {
         let user_in_db = match self.find_user_by_name(username)? {
@@ -95,6 +599,10 @@ impl DatabaseHandler {
             return Err("Wrong password!".to_string());
         }
 
+        if user_in_db.suspended {
+            return Err("This account is suspended!".to_string());
+        }
+
         Ok(user_in_db)
     }
 
@@ -116,12 +624,141 @@ impl DatabaseHandler {
             .map_err(err_to_string)
     }
 
+    /// Get the post that an image belongs to.
+    pub fn find_post_by_image(&self, image: &str) -> Result<Option<Post>, String> {
+        self.posts
+            .find_one(doc! { "image": image }, None)
+            .map_err(err_to_string)
+    }
+
+    /// Get several posts from the database via their ids.
+    pub fn find_posts_by_ids(&self, ids: &[ObjectId]) -> Result<Vec<Post>, String> {
+        self.posts
+            .find(doc! { "_id": { "$in": ids } }, None)
+            .map_err(err_to_string)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(err_to_string)
+    }
+
+    /// Get all posts that have expired.
+    pub fn find_expired_posts(&self) -> Result<Vec<Post>, String> {
+        self.posts
+            .find(doc! { "expires_at": { "$lte": DateTime::now() } }, None)
+            .map_err(err_to_string)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(err_to_string)
+    }
+
+    /// Get a page of posts written by an author, newest first and including hidden ones, as the author sees them.
+    /// `cursor` is the `next_cursor` of the previous page, or `None` to get the first page.
+    pub fn find_posts_by_author(
+        &self,
+        author: &ObjectId,
+        cursor: Option<&str>,
+        limit: i64,
+    ) -> Result<Page<Post>, String> {
+        self.find_posts_by_authors(&[*author], &[], true, cursor, limit)
+    }
+
+    /// Get a page of posts written by any of the given authors, newest first.
+    /// Posts with any of the `hidden_warnings` are left out, and so are hidden posts unless `include_hidden` is set.
+    /// `cursor` is the `next_cursor` of the previous page, or `None` to get the first page.
+    pub fn find_posts_by_authors(
+        &self,
+        authors: &[ObjectId],
+        hidden_warnings: &[ContentWarning],
+        include_hidden: bool,
+        cursor: Option<&str>,
+        limit: i64,
+    ) -> Result<Page<Post>, String> {
+        let mut filter = doc! {
+            "author": { "$in": authors },
+            "content_warning": { "$nin": to_bson(hidden_warnings).map_err(err_to_string)? },
+        };
+        if !include_hidden {
+            filter.insert("hidden", doc! { "$ne": true });
+        }
+
+        self.find_posts_page(filter, cursor, limit)
+    }
+
+    /// Get a page of all posts, newest first, including hidden ones. This is meant for moderators.
+    /// `cursor` is the `next_cursor` of the previous page, or `None` to get the first page.
+    pub fn find_latest_posts(
+        &self,
+        cursor: Option<&str>,
+        limit: i64,
+    ) -> Result<Page<Post>, String> {
+        self.find_posts_page(Document::new(), cursor, limit)
+    }
+
+    /// Get a page of the unexpired posts that match a filter, newest first.
+    fn find_posts_page(
+        &self,
+        mut filter: Document,
+        cursor: Option<&str>,
+        limit: i64,
+    ) -> Result<Page<Post>, String> {
+        filter.extend(not_expired());
+        if let Some(cursor) = cursor {
+            filter.extend(Cursor::decode(cursor)?.filter());
+        }
+
+        // Fetch one post more than asked for, to know if there is another page.
+        let options = FindOptions::builder()
+            .sort(doc! { "created_at": -1, "_id": -1 })
+            .limit(limit + 1)
+            .build();
+
+        let posts = self
+            .posts
+            .find(filter, options)
+            .map_err(err_to_string)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(err_to_string)?;
+
+        Ok(Page::from_overfetched(posts, limit, |post| {
+            post.id.map(|id| Cursor {
+                created_at: post.created_at,
+                id,
+            })
+        }))
+    }
+
+    /// Change the content of a post, and with it its topics. Only the author of the post is allowed to edit it.
+    /// A post that is excluded from discovery stays excluded, even if `exclude_from_discovery` is false.
+    pub fn edit_post(
+        &self,
+        user_editing_post: &ObjectId,
+        post_id: &ObjectId,
+        content: &str,
+        exclude_from_discovery: bool,
+    ) -> Result<UpdateResult, String> {
+        let mut changes = doc! {
+            "content": content,
+            "edited_at": DateTime::now(),
+            "topics": parse_topics(content),
+        };
+        if exclude_from_discovery {
+            changes.insert("excluded_from_discovery", true);
+        }
+
+        self.posts
+            .update_one(
+                doc! { "_id": post_id, "author": user_editing_post },
+                doc! { "$set": changes },
+                None,
+            )
+            .map_err(err_to_string)
+    }
+
     /// Delete a post from the database. Only the author of the post is allowed to delete it.
+    /// Returns the deleted post, so that its image can be deleted too.
     pub fn delete_post(
         &self,
         user_deleting_post: &ObjectId,
         post_id: &ObjectId,
-    ) -> Result<DeleteResult, String> {
+    ) -> Result<Post, String> {
         let post = self.find_post_by_id(post_id).map_err(err_to_string)?;
 
         if post.is_none() {
@@ -135,17 +772,49 @@ impl DatabaseHandler {
             return Err("Deleter is not author of post!".to_string());
         }
 
-        self.posts
-            .delete_one(doc! { "_id": post.id }, None)
-            .map_err(err_to_string)
+        self.remove_post(post_id)?
+            .ok_or_else(|| "Post not found!".to_string())
+    }
+
+    /// Fetch a random post for a viewer from the database.
+    /// Posts that have been hidden, e.g. because of reports, excluded from discovery or expired are never picked.
+    /// Neither are posts by users the viewer has blocked or muted, who have blocked the viewer, or who have private accounts,
+    /// nor posts with content warnings that the viewer always hides.
+    pub fn find_random_post(&self, viewer: &ObjectId) -> Result<Post, String> {
+        self.sample_post(viewer, Document::new())
+    }
+
+    /// Fetch a random post about at least one of `topics` for a viewer. The same posts are left out as in `find_random_post`.
+    pub fn find_random_post_in_topics(
+        &self,
+        viewer: &ObjectId,
+        topics: &[String],
+    ) -> Result<Post, String> {
+        self.sample_post(viewer, doc! { "topics": { "$in": topics } })
     }
 
-    /// Fetch a random post from the database.
-    pub fn find_random_post(&self) -> Result<Post, String> {
+    /// Pick a random post that matches `filter` and that the viewer may discover.
+    fn sample_post(&self, viewer: &ObjectId, mut filter: Document) -> Result<Post, String> {
+        let hidden_authors = self.find_hidden_users(viewer)?;
+        let hidden_warnings = self
+            .find_user_by_id(viewer)?
+            .map(|user| user.preferences.hidden_warnings)
+            .unwrap_or_default();
+
+        filter.extend(doc! {
+            "hidden": { "$ne": true },
+            "excluded_from_discovery": { "$ne": true },
+            "author_private": { "$ne": true },
+            "author": { "$nin": hidden_authors },
+            "content_warning": { "$nin": to_bson(&hidden_warnings).map_err(err_to_string)? },
+        });
+        filter.extend(not_expired());
+
         // This aggregates one random post.
-        let aggregation = self
-            .posts
-            .aggregate([doc! { "$sample": { "size": 1 } }], None);
+        let aggregation = self.posts.aggregate(
+            [doc! { "$match": filter }, doc! { "$sample": { "size": 1 } }],
+            None,
+        );
 
         // Check if the aggregation returned a result or an error.
         // If a result, handle it to read the result and turn it into a Post.
@@ -174,18 +843,24 @@ impl DatabaseHandler {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::BreadConfig;
 
     /// This tests checks that it is possible to save a user to the database.
     #[test]
     fn create_and_save_user_and_post() {
         // Try to connect to the database, panic if it fails.
-        let db_handler = match DatabaseHandler::create_connection() {
-            Ok(handler) => handler,
-            Err(err) => panic!("Could not connect to the database! Error: {}", err),
-        };
+        let db_handler =
+            match DatabaseHandler::create_connection(&BreadConfig::default().database_name) {
+                Ok(handler) => handler,
+                Err(err) => panic!("Could not connect to the database! Error: {}", err),
+            };
 
         // Create a dummy user for the test.
-        let user = match User::create("Foo".to_string(), "Bar".to_string()) {
+        let user = match User::create(
+            "Foo".to_string(),
+            "Bar".to_string(),
+            &Argon2Config::default(),
+        ) {
             Ok(user) => user,
             Err(err) => panic!("Could not create user! {:?}", err),
         };
@@ -197,7 +872,7 @@ mod tests {
         };
 
         // Create a dummy post for the test. (It will not use a real user!)
-        let post = Post::create(user_id, Some("Foo".to_string()), Some(()));
+        let post = Post::create(user_id, Some("Foo".to_string()), None);
 
         // Try to save the user, if it fails, panic.
         match db_handler.save_post(&post) {
@@ -210,13 +885,18 @@ mod tests {
     #[test]
     fn create_save_and_delete_user_and_post() {
         // Try to connect to the database, panic if it fails.
-        let db_handler = match DatabaseHandler::create_connection() {
-            Ok(handler) => handler,
-            Err(err) => panic!("Could not connect to the database! Error: {}", err),
-        };
+        let db_handler =
+            match DatabaseHandler::create_connection(&BreadConfig::default().database_name) {
+                Ok(handler) => handler,
+                Err(err) => panic!("Could not connect to the database! Error: {}", err),
+            };
 
         // Create a dummy user for the test.
-        let user = match User::create("Foo".to_string(), "Bar".to_string()) {
+        let user = match User::create(
+            "Foo".to_string(),
+            "Bar".to_string(),
+            &Argon2Config::default(),
+        ) {
             Ok(user) => user,
             Err(err) => panic!("Could not create user! {:?}", err),
         };
@@ -228,7 +908,7 @@ mod tests {
         };
 
         // Create a dummy post for the test. (It will not use a real user!)
-        let post = Post::create(user_id, Some("Foo".to_string()), Some(()));
+        let post = Post::create(user_id, Some("Foo".to_string()), None);
 
         // Try to save the user, if it fails, panic.
         let post_id = match db_handler.save_post(&post) {
@@ -250,12 +930,13 @@ mod tests {
     /// Make sure it is possible to find a random post.
     #[test]
     fn find_random_post() {
-        let db_handler = match DatabaseHandler::create_connection() {
-            Ok(handler) => handler,
-            Err(err) => panic!("Could not connect to the database! Error: {}", err),
-        };
+        let db_handler =
+            match DatabaseHandler::create_connection(&BreadConfig::default().database_name) {
+                Ok(handler) => handler,
+                Err(err) => panic!("Could not connect to the database! Error: {}", err),
+            };
 
-        match db_handler.find_random_post() {
+        match db_handler.find_random_post(&ObjectId::new()) {
             Ok(post) => println!("Success! Found post: {:?}", post),
             Err(err) => panic!("{}", err),
         };
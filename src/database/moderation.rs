@@ -0,0 +1,86 @@
+use mongodb::{
+    bson::{doc, oid::ObjectId, to_bson},
+    options::FindOptions,
+    results::UpdateResult,
+};
+
+use crate::models::{
+    audit::AuditEntry,
+    post::Post,
+    user::{Role, User},
+};
+
+use super::{err_to_string, DatabaseHandler};
+
+impl DatabaseHandler {
+    /// Get all users, sorted by name.
+    pub fn find_all_users(&self) -> Result<Vec<User>, String> {
+        let options = FindOptions::builder().sort(doc! { "name": 1 }).build();
+
+        self.users
+            .find(None, options)
+            .map_err(err_to_string)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(err_to_string)
+    }
+
+    /// Suspend or unsuspend a user. A suspended user can't log in or use the API.
+    pub fn set_user_suspended(
+        &self,
+        user_id: &ObjectId,
+        suspended: bool,
+    ) -> Result<UpdateResult, String> {
+        self.users
+            .update_one(
+                doc! { "_id": user_id },
+                doc! { "$set": { "suspended": suspended } },
+                None,
+            )
+            .map_err(err_to_string)
+    }
+
+    /// Change what a user is allowed to do.
+    pub fn set_user_role(&self, user_id: &ObjectId, role: Role) -> Result<UpdateResult, String> {
+        self.users
+            .update_one(
+                doc! { "_id": user_id },
+                doc! { "$set": { "role": to_bson(&role).map_err(err_to_string)? } },
+                None,
+            )
+            .map_err(err_to_string)
+    }
+
+    /// Remove a post regardless of who wrote it, along with its reactions. This is meant for moderators.
+    /// Returns the removed post, so that its image can be deleted too, or `None` if there was no such post.
+    pub fn remove_post(&self, post_id: &ObjectId) -> Result<Option<Post>, String> {
+        self.reactions
+            .delete_many(doc! { "post": post_id }, None)
+            .map_err(err_to_string)?;
+        self.posts
+            .find_one_and_delete(doc! { "_id": post_id }, None)
+            .map_err(err_to_string)
+    }
+
+    /// Saves an entry to the audit trail.
+    pub fn save_audit_entry(&self, entry: &AuditEntry) -> Result<Option<ObjectId>, String> {
+        let result = self
+            .audit_log
+            .insert_one(entry, None)
+            .map_err(err_to_string)?;
+        Ok(result.inserted_id.as_object_id())
+    }
+
+    /// Get the latest entries of the audit trail, newest first.
+    pub fn find_audit_entries(&self, limit: i64) -> Result<Vec<AuditEntry>, String> {
+        let options = FindOptions::builder()
+            .sort(doc! { "timestamp": -1 })
+            .limit(limit)
+            .build();
+
+        self.audit_log
+            .find(None, options)
+            .map_err(err_to_string)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(err_to_string)
+    }
+}
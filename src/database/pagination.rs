@@ -0,0 +1,173 @@
+use mongodb::bson::{doc, oid::ObjectId, DateTime, Document};
+use serde::Serialize;
+
+/// A cursor that can be handed to clients as an opaque string, so that they don't come to rely on what's in it.
+pub trait PageCursor {
+    fn encode(&self) -> String;
+}
+
+/// Encode eight bytes and an id as a string of 40 hexadecimal digits.
+fn encode_parts(first: [u8; 8], id: &ObjectId) -> String {
+    first
+        .iter()
+        .chain(id.bytes().iter())
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Decode a string that was made by `encode_parts`.
+fn decode_parts(encoded: &str) -> Result<([u8; 8], ObjectId), String> {
+    if encoded.len() != 40 || !encoded.is_ascii() {
+        return Err("Invalid cursor!".to_string());
+    }
+
+    let bytes = (0..encoded.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&encoded[i..i + 2], 16))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| "Invalid cursor!".to_string())?;
+
+    let mut first = [0; 8];
+    first.copy_from_slice(&bytes[..8]);
+    let mut id = [0; 12];
+    id.copy_from_slice(&bytes[8..]);
+
+    Ok((first, ObjectId::from_bytes(id)))
+}
+
+/// A position in a list of documents sorted by `created_at` and `_id`, newest first.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Cursor {
+    pub created_at: DateTime,
+    pub id: ObjectId,
+}
+
+impl PageCursor for Cursor {
+    fn encode(&self) -> String {
+        encode_parts(self.created_at.timestamp_millis().to_be_bytes(), &self.id)
+    }
+}
+
+impl Cursor {
+    /// Decode a cursor that was made by `Cursor::encode`.
+    pub fn decode(encoded: &str) -> Result<Self, String> {
+        let (millis, id) = decode_parts(encoded)?;
+
+        Ok(Cursor {
+            created_at: DateTime::from_millis(i64::from_be_bytes(millis)),
+            id,
+        })
+    }
+
+    /// A filter that matches the documents that come after the cursor.
+    pub fn filter(&self) -> Document {
+        doc! {
+            "$or": [
+                { "created_at": { "$lt": self.created_at } },
+                { "created_at": self.created_at, "_id": { "$lt": self.id } },
+            ]
+        }
+    }
+}
+
+/// A position in a list of search results sorted by their relevance `score` and `_id`, most relevant first.
+#[derive(Debug, PartialEq)]
+pub struct RelevanceCursor {
+    pub score: f64,
+    pub id: ObjectId,
+}
+
+impl PageCursor for RelevanceCursor {
+    fn encode(&self) -> String {
+        encode_parts(self.score.to_bits().to_be_bytes(), &self.id)
+    }
+}
+
+impl RelevanceCursor {
+    /// Decode a cursor that was made by `RelevanceCursor::encode`.
+    pub fn decode(encoded: &str) -> Result<Self, String> {
+        let (score, id) = decode_parts(encoded)?;
+
+        Ok(RelevanceCursor {
+            score: f64::from_bits(u64::from_be_bytes(score)),
+            id,
+        })
+    }
+
+    /// A filter that matches the results that come after the cursor.
+    pub fn filter(&self) -> Document {
+        doc! {
+            "$or": [
+                { "score": { "$lt": self.score } },
+                { "score": self.score, "_id": { "$lt": self.id } },
+            ]
+        }
+    }
+}
+
+/// A page of results, along with the cursor to get the next page with.
+/// `next_cursor` is `None` on the last page.
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+}
+
+impl<T> Page<T> {
+    /// Make a page out of up to `limit + 1` items, where the extra item only tells that there is another page.
+    /// `cursor_of` gives the cursor pointing at an item.
+    pub fn from_overfetched<C: PageCursor>(
+        mut items: Vec<T>,
+        limit: i64,
+        cursor_of: impl Fn(&T) -> Option<C>,
+    ) -> Self {
+        let next_cursor = if items.len() as i64 > limit {
+            items.truncate(limit as usize);
+            items
+                .last()
+                .and_then(cursor_of)
+                .map(|cursor| cursor.encode())
+        } else {
+            None
+        };
+
+        Page { items, next_cursor }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A cursor should survive being encoded and decoded.
+    #[test]
+    fn encode_and_decode_cursor() {
+        let cursor = Cursor {
+            created_at: DateTime::now(),
+            id: ObjectId::new(),
+        };
+
+        assert_eq!(Cursor::decode(&cursor.encode()), Ok(cursor));
+    }
+
+    /// The score is kept exactly, so that the next page starts right after the last result.
+    #[test]
+    fn encode_and_decode_relevance_cursor() {
+        let cursor = RelevanceCursor {
+            score: 1.0 / 3.0,
+            id: ObjectId::new(),
+        };
+
+        assert_eq!(RelevanceCursor::decode(&cursor.encode()), Ok(cursor));
+    }
+
+    /// Garbage should be rejected instead of being turned into a cursor.
+    #[test]
+    fn decode_invalid_cursor() {
+        assert!(Cursor::decode("").is_err());
+        assert!(Cursor::decode("not a cursor").is_err());
+        assert!(Cursor::decode(&"zz".repeat(20)).is_err());
+        assert!(Cursor::decode(&"é".repeat(20)).is_err());
+    }
+}
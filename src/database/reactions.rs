@@ -0,0 +1,63 @@
+use mongodb::{
+    bson::{doc, oid::ObjectId, to_bson},
+    options::{FindOptions, UpdateOptions},
+    results::DeleteResult,
+};
+
+use crate::models::reaction::Reaction;
+
+use super::{err_to_string, DatabaseHandler};
+
+impl DatabaseHandler {
+    /// Saves a reaction to the database.
+    /// A user's earlier reaction to the same post is replaced, so reacting twice does nothing.
+    pub fn save_reaction(&self, reaction: &Reaction) -> Result<(), String> {
+        let options = UpdateOptions::builder().upsert(true).build();
+        self.reactions
+            .update_one(
+                doc! { "post": reaction.post, "reactor": reaction.reactor },
+                doc! {
+                    "$set": { "kind": to_bson(&reaction.kind).map_err(err_to_string)? },
+                    "$setOnInsert": { "created_at": reaction.created_at },
+                },
+                options,
+            )
+            .map_err(err_to_string)?;
+
+        Ok(())
+    }
+
+    /// Delete a user's reaction to a post.
+    pub fn delete_reaction(
+        &self,
+        post: &ObjectId,
+        reactor: &ObjectId,
+    ) -> Result<DeleteResult, String> {
+        self.reactions
+            .delete_one(doc! { "post": post, "reactor": reactor }, None)
+            .map_err(err_to_string)
+    }
+
+    /// Get a user's reaction to a post, if they have reacted to it.
+    pub fn find_reaction(
+        &self,
+        post: &ObjectId,
+        reactor: &ObjectId,
+    ) -> Result<Option<Reaction>, String> {
+        self.reactions
+            .find_one(doc! { "post": post, "reactor": reactor }, None)
+            .map_err(err_to_string)
+    }
+
+    /// Get all reactions to some posts, newest first.
+    pub fn find_reactions_to_posts(&self, posts: &[ObjectId]) -> Result<Vec<Reaction>, String> {
+        let options = FindOptions::builder()
+            .sort(doc! { "created_at": -1 })
+            .build();
+        self.reactions
+            .find(doc! { "post": { "$in": posts } }, options)
+            .map_err(err_to_string)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(err_to_string)
+    }
+}
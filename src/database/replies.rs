@@ -0,0 +1,113 @@
+use mongodb::{
+    bson::{doc, oid::ObjectId, DateTime},
+    options::{FindOptions, UpdateOptions},
+    results::UpdateResult,
+};
+
+use crate::models::reply::Reply;
+
+use super::{
+    err_to_string,
+    pagination::{Cursor, Page},
+    DatabaseHandler,
+};
+
+/// How long the hours that replies are counted in are.
+const HOUR_MILLIS: i64 = 60 * 60 * 1000;
+
+impl DatabaseHandler {
+    /// Saves a reply to the database.
+    /// Returns the id of the created reply as an option.
+    pub fn save_reply(&self, reply: &Reply) -> Result<Option<ObjectId>, String> {
+        let result = self
+            .replies
+            .insert_one(reply, None)
+            .map_err(err_to_string)?;
+        Ok(result.inserted_id.as_object_id())
+    }
+
+    /// Count a reply a user is about to send in the current hour, unless they have already sent `limit` in it.
+    /// Returns whether they may send it. Checking and counting happen in one update, like `take_quota`,
+    /// so that concurrent replies can't both take the last one.
+    pub fn take_reply_slot(&self, from: &ObjectId, limit: u32) -> Result<bool, String> {
+        let now = DateTime::now().timestamp_millis();
+        let hour = DateTime::from_millis(now - now.rem_euclid(HOUR_MILLIS));
+
+        // The hour's document has to exist first. An upsert that didn't match because the limit is reached would insert a second one.
+        let options = UpdateOptions::builder().upsert(true).build();
+        self.hourly_replies
+            .update_one(
+                doc! { "from": from, "hour": hour },
+                doc! { "$setOnInsert": { "sent": 0 } },
+                options,
+            )
+            .map_err(err_to_string)?;
+
+        let result = self
+            .hourly_replies
+            .update_one(
+                doc! { "from": from, "hour": hour, "sent": { "$lt": i64::from(limit) } },
+                doc! { "$inc": { "sent": 1 } },
+                None,
+            )
+            .map_err(err_to_string)?;
+
+        Ok(result.modified_count == 1)
+    }
+
+    /// Count a user's unread replies.
+    pub fn count_unread_replies(&self, to: &ObjectId) -> Result<u64, String> {
+        self.replies
+            .count_documents(doc! { "to": to, "read": false }, None)
+            .map_err(err_to_string)
+    }
+
+    /// Get a page of the replies a user has received, newest first.
+    /// `cursor` is the `next_cursor` of the previous page, or `None` to get the first page.
+    pub fn find_replies_to(
+        &self,
+        to: &ObjectId,
+        cursor: Option<&str>,
+        limit: i64,
+    ) -> Result<Page<Reply>, String> {
+        let mut filter = doc! { "to": to };
+        if let Some(cursor) = cursor {
+            filter.extend(Cursor::decode(cursor)?.filter());
+        }
+
+        // Fetch one reply more than asked for, to know if there is another page.
+        let options = FindOptions::builder()
+            .sort(doc! { "created_at": -1, "_id": -1 })
+            .limit(limit + 1)
+            .build();
+
+        let replies = self
+            .replies
+            .find(filter, options)
+            .map_err(err_to_string)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(err_to_string)?;
+
+        Ok(Page::from_overfetched(replies, limit, |reply| {
+            reply.id.map(|id| Cursor {
+                created_at: reply.created_at,
+                id,
+            })
+        }))
+    }
+
+    /// Mark a reply as read. Only the recipient of the reply can do this.
+    pub fn mark_reply_read(
+        &self,
+        to: &ObjectId,
+        reply_id: &ObjectId,
+    ) -> Result<UpdateResult, String> {
+        self.replies
+            .update_one(
+                doc! { "_id": reply_id, "to": to },
+                doc! { "$set": { "read": true } },
+                None,
+            )
+            .map_err(err_to_string)
+    }
+}
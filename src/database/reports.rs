@@ -0,0 +1,114 @@
+use mongodb::{
+    bson::{doc, oid::ObjectId, to_document},
+    options::{FindOptions, IndexOptions, UpdateOptions},
+    results::UpdateResult,
+    IndexModel,
+};
+
+use crate::models::report::Report;
+
+use super::{err_to_string, DatabaseHandler};
+
+/// The name of the index that lets each user have only one open report of a post.
+const OPEN_REPORT_INDEX: &str = "open_report_per_reporter";
+
+/// The name of the index that used to let each user report a post only once, ever.
+const OLD_REPORT_INDEX: &str = "post_1_reporter_1";
+
+impl DatabaseHandler {
+    /// Make sure each user has at most one open report of a post.
+    /// Once their report is resolved they may report the post again, e.g. if it was edited.
+    pub(super) fn create_report_index(&self) -> Result<(), String> {
+        let has_old_index = self
+            .reports
+            .list_index_names()
+            .map_err(err_to_string)?
+            .iter()
+            .any(|name| name == OLD_REPORT_INDEX);
+        if has_old_index {
+            self.reports
+                .drop_index(OLD_REPORT_INDEX, None)
+                .map_err(err_to_string)?;
+        }
+
+        self.reports
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "post": 1, "reporter": 1 })
+                    .options(
+                        IndexOptions::builder()
+                            .name(OPEN_REPORT_INDEX.to_string())
+                            .unique(true)
+                            .partial_filter_expression(doc! { "resolved": false })
+                            .build(),
+                    )
+                    .build(),
+                None,
+            )
+            .map_err(err_to_string)?;
+
+        Ok(())
+    }
+
+    /// Saves a report to the database, unless the reporter already has an open report of the post.
+    /// Returns whether the report was new.
+    pub fn save_report(&self, report: &Report) -> Result<bool, String> {
+        let options = UpdateOptions::builder().upsert(true).build();
+        let result = self
+            .reports
+            .update_one(
+                doc! { "post": report.post, "reporter": report.reporter, "resolved": false },
+                doc! { "$setOnInsert": to_document(report).map_err(err_to_string)? },
+                options,
+            )
+            .map_err(err_to_string)?;
+
+        Ok(result.upserted_id.is_some())
+    }
+
+    /// Count the unresolved reports of a post.
+    pub fn count_open_reports(&self, post_id: &ObjectId) -> Result<u64, String> {
+        self.reports
+            .count_documents(doc! { "post": post_id, "resolved": false }, None)
+            .map_err(err_to_string)
+    }
+
+    /// Get all unresolved reports, oldest first.
+    pub fn find_open_reports(&self) -> Result<Vec<Report>, String> {
+        let options = FindOptions::builder()
+            .sort(doc! { "created_at": 1 })
+            .build();
+
+        self.reports
+            .find(doc! { "resolved": false }, options)
+            .map_err(err_to_string)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(err_to_string)
+    }
+
+    /// Mark all reports of a post as resolved.
+    pub fn resolve_reports(&self, post_id: &ObjectId) -> Result<UpdateResult, String> {
+        self.reports
+            .update_many(
+                doc! { "post": post_id, "resolved": false },
+                doc! { "$set": { "resolved": true } },
+                None,
+            )
+            .map_err(err_to_string)
+    }
+
+    /// Hide or unhide a post. Hidden posts are never picked by `find_random_post`.
+    pub fn set_post_hidden(
+        &self,
+        post_id: &ObjectId,
+        hidden: bool,
+    ) -> Result<UpdateResult, String> {
+        self.posts
+            .update_one(
+                doc! { "_id": post_id },
+                doc! { "$set": { "hidden": hidden } },
+                None,
+            )
+            .map_err(err_to_string)
+    }
+}
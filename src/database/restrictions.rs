@@ -0,0 +1,133 @@
+use mongodb::{
+    bson::{doc, oid::ObjectId, to_bson, to_document},
+    options::UpdateOptions,
+    results::DeleteResult,
+};
+
+use crate::models::restriction::{Restriction, RestrictionKind};
+
+use super::{err_to_string, DatabaseHandler};
+
+impl DatabaseHandler {
+    /// Saves a block or mute to the database. Blocking or muting someone twice does nothing.
+    pub fn save_restriction(&self, restriction: &Restriction) -> Result<(), String> {
+        let options = UpdateOptions::builder().upsert(true).build();
+        self.restrictions
+            .update_one(
+                doc! {
+                    "user": restriction.user,
+                    "target": restriction.target,
+                    "kind": to_bson(&restriction.kind).map_err(err_to_string)?,
+                },
+                doc! { "$setOnInsert": to_document(restriction).map_err(err_to_string)? },
+                options,
+            )
+            .map_err(err_to_string)?;
+
+        Ok(())
+    }
+
+    /// Delete a block or mute from the database.
+    pub fn delete_restriction(
+        &self,
+        user: &ObjectId,
+        target: &ObjectId,
+        kind: RestrictionKind,
+    ) -> Result<DeleteResult, String> {
+        self.restrictions
+            .delete_one(
+                doc! {
+                    "user": user,
+                    "target": target,
+                    "kind": to_bson(&kind).map_err(err_to_string)?,
+                },
+                None,
+            )
+            .map_err(err_to_string)
+    }
+
+    /// Check if either of two users has blocked the other.
+    pub fn is_blocked_between(&self, a: &ObjectId, b: &ObjectId) -> Result<bool, String> {
+        self.restrictions
+            .count_documents(
+                doc! {
+                    "kind": to_bson(&RestrictionKind::Block).map_err(err_to_string)?,
+                    "$or": [
+                        { "user": a, "target": b },
+                        { "user": b, "target": a },
+                    ],
+                },
+                None,
+            )
+            .map(|count| count > 0)
+            .map_err(err_to_string)
+    }
+
+    /// Get the ids of the users that a user has blocked or muted.
+    pub fn find_restricted_users(
+        &self,
+        user: &ObjectId,
+        kind: RestrictionKind,
+    ) -> Result<Vec<ObjectId>, String> {
+        self.restrictions
+            .find(
+                doc! { "user": user, "kind": to_bson(&kind).map_err(err_to_string)? },
+                None,
+            )
+            .map_err(err_to_string)?
+            .map(|restriction| {
+                restriction
+                    .map(|restriction| restriction.target)
+                    .map_err(err_to_string)
+            })
+            .collect()
+    }
+
+    /// Get the ids of all users whose posts a user shouldn't see:
+    /// the users they have blocked or muted, and the users who have blocked them.
+    pub fn find_hidden_users(&self, viewer: &ObjectId) -> Result<Vec<ObjectId>, String> {
+        let block = to_bson(&RestrictionKind::Block).map_err(err_to_string)?;
+
+        self.restrictions
+            .find(
+                doc! { "$or": [
+                    { "user": viewer },
+                    { "target": viewer, "kind": block },
+                ] },
+                None,
+            )
+            .map_err(err_to_string)?
+            .filter_map(|restriction| match restriction {
+                Ok(restriction) => restriction.hides_from(viewer).map(Ok),
+                Err(err) => Some(Err(err_to_string(err))),
+            })
+            .collect()
+    }
+
+    /// Get the ids of the users that a user has blocked, and of the users who have blocked them.
+    pub fn find_blocked_or_blocking(&self, user: &ObjectId) -> Result<Vec<ObjectId>, String> {
+        let block = to_bson(&RestrictionKind::Block).map_err(err_to_string)?;
+
+        self.restrictions
+            .find(
+                doc! {
+                    "kind": block,
+                    "$or": [{ "user": user }, { "target": user }],
+                },
+                None,
+            )
+            .map_err(err_to_string)?
+            .map(|restriction| {
+                restriction
+                    .map(|restriction| {
+                        if &restriction.user == user {
+                            restriction.target
+                        } else {
+                            restriction.user
+                        }
+                    })
+                    .map_err(err_to_string)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,419 @@
+use mongodb::{
+    bson::{doc, from_document, oid::ObjectId, to_bson, DateTime, Document},
+    options::{Collation, CollationStrength, FindOptions, IndexOptions},
+    IndexModel,
+};
+
+use crate::models::{
+    post::Post,
+    user::{Role, User},
+};
+
+use super::{
+    err_to_string, not_expired,
+    pagination::{Page, RelevanceCursor},
+    DatabaseHandler,
+};
+
+/// How many users are compared to a query at most when looking for names with typos in them.
+const TYPO_CANDIDATES: i64 = 200;
+
+/// The name of the text index on the content of posts. A collection can only have one text index.
+const POST_TEXT_INDEX: &str = "content_text";
+
+/// Narrows down a search for posts.
+#[derive(Debug, Default)]
+pub struct PostFilters {
+    pub author: Option<ObjectId>,
+    /// Only find posts created at or after this time.
+    pub since: Option<DateTime>,
+    /// Only find posts created before this time.
+    pub until: Option<DateTime>,
+}
+
+/// Usernames are searched without regard to case, so that "bread" also finds "Bread".
+/// The index on `name` uses the same collation, queries have to use it too to make use of the index.
+pub(super) fn name_collation() -> Collation {
+    Collation::builder()
+        .locale("en")
+        .strength(CollationStrength::Secondary)
+        .build()
+}
+
+/// A filter for names that start with `prefix`. Only works together with `name_collation`,
+/// which sorts U+FFFF after every other character so that it closes the range.
+fn name_starts_with(prefix: &str) -> Document {
+    doc! { "name": { "$gte": prefix, "$lt": format!("{}\u{ffff}", prefix) } }
+}
+
+/// How many typos a query may contain and still find a name. Short queries have to be spelled right.
+fn allowed_typos(query: &str) -> usize {
+    match query.chars().count() {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
+}
+
+/// Count the characters that have to be inserted, removed or replaced to turn one string into the other, ignoring case.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.to_lowercase().chars().collect();
+    let b: Vec<char> = b.to_lowercase().chars().collect();
+
+    // Only the previous row of the distance matrix has to be kept.
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.iter().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let replace = previous[j] + usize::from(a_char != b_char);
+            current.push(replace.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+impl DatabaseHandler {
+    /// Search for users by name on behalf of a viewer.
+    /// Users whose names start with the query come first, sorted by name. If there are fewer than `limit` of them,
+    /// they are followed by users whose names are a typo or two away from the query, closest first.
+    /// The viewer, suspended users, and users who have blocked or been blocked by the viewer are never found.
+    /// Private accounts are only found by their exact name, unless the viewer already follows them.
+    pub fn search_users(
+        &self,
+        viewer: &ObjectId,
+        query: &str,
+        limit: i64,
+    ) -> Result<Vec<User>, String> {
+        let mut excluded = self.find_blocked_or_blocking(viewer)?;
+        excluded.push(*viewer);
+        let visible = doc! {
+            "_id": { "$nin": excluded },
+            "suspended": { "$ne": true },
+            "$or": [
+                { "preferences.is_private": { "$ne": true } },
+                { "_id": { "$in": self.find_followees(viewer)? } },
+                { "name": query },
+            ],
+        };
+
+        let mut filter = name_starts_with(query);
+        filter.extend(visible.clone());
+        let options = FindOptions::builder()
+            .collation(name_collation())
+            .sort(doc! { "name": 1 })
+            .limit(limit)
+            .build();
+        let mut users = self
+            .users
+            .find(filter, options)
+            .map_err(err_to_string)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(err_to_string)?;
+
+        let typos = allowed_typos(query);
+        let first_char = match query.chars().next() {
+            Some(first_char) if typos > 0 && users.len() < limit as usize => first_char,
+            _ => return Ok(users),
+        };
+
+        // Only names with the same first letter and about the same length can be close enough.
+        // Comparing them in the database isn't possible, so the number of names compared is capped.
+        let length = query.chars().count();
+        let mut filter = name_starts_with(&first_char.to_string());
+        filter.extend(visible);
+        filter.insert(
+            "$expr",
+            doc! { "$and": [
+                { "$gte": [{ "$strLenCP": "$name" }, (length - typos) as i64] },
+                { "$lte": [{ "$strLenCP": "$name" }, (length + typos) as i64] },
+            ] },
+        );
+        let options = FindOptions::builder()
+            .collation(name_collation())
+            .limit(TYPO_CANDIDATES)
+            .build();
+
+        let mut similar = self
+            .users
+            .find(filter, options)
+            .map_err(err_to_string)?
+            .filter_map(|user| match user {
+                Ok(user) if users.iter().any(|found| found.id == user.id) => None,
+                Ok(user) => {
+                    let distance = edit_distance(query, &user.name);
+                    (distance <= typos).then_some(Ok((distance, user)))
+                }
+                Err(err) => Some(Err(err_to_string(err))),
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        similar.sort_by(|(a_distance, a), (b_distance, b)| {
+            a_distance
+                .cmp(b_distance)
+                .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+        });
+
+        users.extend(
+            similar
+                .into_iter()
+                .map(|(_, user)| user)
+                .take(limit as usize - users.len()),
+        );
+
+        Ok(users)
+    }
+
+    /// Make sure that posts can be searched by their content, with words stemmed the way they are in `language`.
+    /// `language` is one of the languages that MongoDB supports for text search, e.g. "english" or "swedish".
+    /// The index is made over if it was made for another language, since changing it in place isn't possible.
+    pub fn create_post_text_index(&self, language: &str) -> Result<(), String> {
+        let is_outdated = self
+            .posts
+            .list_indexes(None)
+            .map_err(err_to_string)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(err_to_string)?
+            .iter()
+            .filter_map(|index| index.options.as_ref())
+            .any(|options| {
+                options.name.as_deref() == Some(POST_TEXT_INDEX)
+                    && options.default_language.as_deref() != Some(language)
+            });
+        if is_outdated {
+            self.posts
+                .drop_index(POST_TEXT_INDEX, None)
+                .map_err(err_to_string)?;
+        }
+
+        self.posts
+            .create_index(
+                IndexModel::builder()
+                    .keys(doc! { "content": "text" })
+                    .options(
+                        IndexOptions::builder()
+                            .name(POST_TEXT_INDEX.to_string())
+                            .default_language(language.to_string())
+                            .build(),
+                    )
+                    .build(),
+                None,
+            )
+            .map_err(err_to_string)?;
+
+        Ok(())
+    }
+
+    /// Search for posts by their content on behalf of a viewer, most relevant first.
+    /// `cursor` is the `next_cursor` of the previous page, or `None` to get the first page.
+    /// Posts by users the viewer has blocked or muted, or who have blocked the viewer, are never found,
+    /// and neither are expired posts. Private accounts' posts are only found by their followers.
+    /// Posts excluded from discovery are only found by the author and their followers,
+    /// and hidden posts only by the author and moderators.
+    /// Others' posts with warnings the viewer always hides are left out.
+    pub fn search_posts(
+        &self,
+        viewer: &User,
+        query: &str,
+        filters: &PostFilters,
+        cursor: Option<&str>,
+        limit: i64,
+    ) -> Result<Page<Post>, String> {
+        let viewer_id = viewer.id.ok_or_else(|| "User has no id!".to_string())?;
+        let mut followed = self.find_followees(&viewer_id)?;
+        followed.push(viewer_id);
+
+        let mut conditions = vec![
+            doc! { "author": { "$nin": self.find_hidden_users(&viewer_id)? } },
+            doc! { "$or": [
+                { "author": { "$in": followed } },
+                {
+                    "author_private": { "$ne": true },
+                    "excluded_from_discovery": { "$ne": true },
+                },
+            ] },
+            not_expired(),
+        ];
+        conditions.push(doc! { "$or": [
+            { "author": viewer_id },
+            { "content_warning": { "$nin": to_bson(&viewer.preferences.hidden_warnings).map_err(err_to_string)? } },
+        ] });
+        if viewer.role < Role::Moderator {
+            conditions
+                .push(doc! { "$or": [{ "author": viewer_id }, { "hidden": { "$ne": true } }] });
+        }
+        if let Some(author) = filters.author {
+            conditions.push(doc! { "author": author });
+        }
+        if let Some(since) = filters.since {
+            conditions.push(doc! { "created_at": { "$gte": since } });
+        }
+        if let Some(until) = filters.until {
+            conditions.push(doc! { "created_at": { "$lt": until } });
+        }
+
+        let mut pipeline = vec![
+            doc! { "$match": { "$text": { "$search": query }, "$and": conditions } },
+            doc! { "$addFields": { "score": { "$meta": "textScore" } } },
+        ];
+        if let Some(cursor) = cursor {
+            pipeline.push(doc! { "$match": RelevanceCursor::decode(cursor)?.filter() });
+        }
+        // Fetch one post more than asked for, to know if there is another page.
+        pipeline.push(doc! { "$sort": { "score": -1, "_id": -1 } });
+        pipeline.push(doc! { "$limit": limit + 1 });
+
+        let results = self
+            .posts
+            .aggregate(pipeline, None)
+            .map_err(err_to_string)?
+            .map(|document| {
+                let document = document.map_err(err_to_string)?;
+                let score = document.get_f64("score").map_err(err_to_string)?;
+                let post = from_document::<Post>(document).map_err(err_to_string)?;
+                Ok((score, post))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let page = Page::from_overfetched(results, limit, |(score, post)| {
+            post.id.map(|id| RelevanceCursor { score: *score, id })
+        });
+
+        Ok(Page {
+            items: page.items.into_iter().map(|(_, post)| post).collect(),
+            next_cursor: page.next_cursor,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        config::BreadConfig,
+        models::{
+            follow::{Follow, FollowStatus},
+            post::ContentWarning,
+            restriction::{Restriction, RestrictionKind},
+            user::{test_user, WarningPreference},
+        },
+    };
+
+    use super::*;
+
+    #[test]
+    fn edit_distance_counts_typos() {
+        assert_eq!(edit_distance("bread", "bread"), 0);
+        assert_eq!(edit_distance("bread", "BREAD"), 0);
+        assert_eq!(edit_distance("bread", "braed"), 2);
+        assert_eq!(edit_distance("bread", "breads"), 1);
+        assert_eq!(edit_distance("bread", "bred"), 1);
+        assert_eq!(edit_distance("bread", "brexd"), 1);
+        assert_eq!(edit_distance("", "bread"), 5);
+    }
+
+    /// Needs a database, like the tests in `database`.
+    #[test]
+    fn post_search_follows_visibility() {
+        let db = DatabaseHandler::create_connection(&BreadConfig::default().database_name)
+            .expect("Could not connect to the database!");
+        db.create_post_text_index("english").unwrap();
+
+        // A made up word, so that only this test's posts are found.
+        let word = format!("crumb{}", ObjectId::new());
+        let mut viewer_user = test_user("Viewer", Role::User);
+        viewer_user
+            .preferences
+            .set_warning_preference(ContentWarning::Grief, WarningPreference::Hide);
+        let viewer = viewer_user.id.unwrap();
+        let (public, followed_private, private, blocking, muted) = (
+            ObjectId::new(),
+            ObjectId::new(),
+            ObjectId::new(),
+            ObjectId::new(),
+            ObjectId::new(),
+        );
+        db.save_follow(&Follow::create(
+            viewer,
+            followed_private,
+            FollowStatus::Accepted,
+        ))
+        .unwrap();
+        db.save_restriction(&Restriction::create(
+            blocking,
+            viewer,
+            RestrictionKind::Block,
+        ))
+        .unwrap();
+        db.save_restriction(&Restriction::create(viewer, muted, RestrictionKind::Mute))
+            .unwrap();
+
+        let save = |author: ObjectId, private: bool, hidden: bool| {
+            let mut post = Post::create(author, Some(format!("Fresh {}", word)), None);
+            post.author_private = private;
+            post.hidden = hidden;
+            db.save_post(&post).unwrap().unwrap()
+        };
+        let found_public = save(public, false, false);
+        let found_followed = save(followed_private, true, false);
+        // Hidden posts are only found by their author and moderators, not even by followers.
+        let followed_hidden = save(followed_private, true, true);
+        let public_hidden = save(public, false, true);
+        let own_hidden = save(viewer, false, true);
+        save(private, true, false);
+        let mut grieving = Post::create(public, Some(format!("Sad {}", word)), None);
+        grieving.content_warning = Some(ContentWarning::Grief);
+        db.save_post(&grieving).unwrap();
+        save(blocking, false, false);
+        save(muted, false, false);
+
+        let find = |viewer: &User| {
+            let mut found: Vec<_> = db
+                .search_posts(viewer, &word, &PostFilters::default(), None, 20)
+                .unwrap()
+                .items
+                .into_iter()
+                .filter_map(|post| post.id)
+                .collect();
+            found.sort();
+            found
+        };
+        let mut expected = vec![found_public, found_followed, own_hidden];
+        expected.sort();
+        assert_eq!(find(&viewer_user), expected);
+
+        let mut moderator = viewer_user.clone();
+        moderator.role = Role::Moderator;
+        let mut expected = vec![
+            found_public,
+            found_followed,
+            followed_hidden,
+            public_hidden,
+            own_hidden,
+        ];
+        expected.sort();
+        assert_eq!(find(&moderator), expected);
+
+        // Filters narrow the visible posts down further.
+        let by_public = PostFilters {
+            author: Some(public),
+            ..Default::default()
+        };
+        let found = db
+            .search_posts(&viewer_user, &word, &by_public, None, 20)
+            .unwrap()
+            .items;
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, Some(found_public));
+
+        for id in [viewer, public, followed_private, private, blocking, muted] {
+            db.delete_account(&id).unwrap();
+        }
+    }
+
+    #[test]
+    fn short_queries_allow_no_typos() {
+        assert_eq!(allowed_typos("bre"), 0);
+        assert_eq!(allowed_typos("bread"), 1);
+        assert_eq!(allowed_typos("sourdough"), 2);
+    }
+}
@@ -0,0 +1,18 @@
+//! Bread, a social media focusing on mental health and privacy.
+//! The server is the `bread` binary and the admin tool is `bread-admin`. Both are built on this library.
+
+#[macro_use]
+extern crate rocket;
+
+pub mod api;
+pub mod app;
+pub mod classifier;
+pub mod config;
+pub mod database;
+pub mod logging;
+pub mod metrics;
+pub mod models;
+pub mod rate_limit;
+pub mod startup;
+pub mod storage;
+pub mod sweeper;
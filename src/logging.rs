@@ -0,0 +1,187 @@
+use std::{io::IsTerminal, time::Instant};
+
+use mongodb::bson::oid::ObjectId;
+use rocket::{
+    config::LogLevel,
+    fairing::{Fairing, Info, Kind},
+    figment::Figment,
+    Data, Request, Response,
+};
+use serde::Deserialize;
+use tracing::level_filters::LevelFilter;
+use tracing_subscriber::{filter::Targets, layer::SubscriberExt, util::SubscriberInitExt};
+
+/// The header a request's ID is sent back in, and can be passed on in by a proxy in front of Bread.
+const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+/// Longer IDs from a proxy are replaced, so that nobody can fill the logs through the header.
+const MAX_REQUEST_ID_LENGTH: usize = 64;
+
+/// How log lines are written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(crate = "rocket::serde", rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Lines for people to read.
+    #[default]
+    Text,
+    /// One JSON object per line, for log collectors.
+    Json,
+}
+
+/// Send all logs, Bread's and Rocket's, through `tracing` in the configured format.
+/// This must happen before Rocket is built, since Rocket only sets up its own logger if no other is set.
+/// An invalid `log_format` falls back to text here, and is reported when the configuration is read.
+pub fn init(figment: &Figment) {
+    let format = figment
+        .extract_inner::<LogFormat>("log_format")
+        .unwrap_or_default();
+    let level = match figment.extract_inner::<LogLevel>("log_level") {
+        Ok(LogLevel::Critical) => LevelFilter::WARN,
+        Ok(LogLevel::Normal) | Err(_) => LevelFilter::INFO,
+        Ok(LogLevel::Debug) => LevelFilter::TRACE,
+        Ok(LogLevel::Off) => LevelFilter::OFF,
+    };
+
+    // Rocket logs each request with its full URI, whose query can hold a username, e.g. when searching.
+    // `RequestLogger` logs requests instead, by their route.
+    let filter = Targets::new()
+        .with_default(level)
+        .with_target("rocket::server", LevelFilter::OFF);
+
+    let layer = tracing_subscriber::fmt::layer().with_ansi(std::io::stdout().is_terminal());
+    let registry = tracing_subscriber::registry().with(filter);
+    // Setting up logging twice only fails in tests, where the first setup is kept.
+    let _ = match format {
+        LogFormat::Text => registry.with(layer).try_init(),
+        LogFormat::Json => registry.with(layer.json().flatten_event(true)).try_init(),
+    };
+}
+
+/// The ID of a request, and when Rocket started handling it.
+struct RequestLog {
+    id: String,
+    start: Instant,
+}
+
+/// The logged in user that a request was made by, if any.
+struct RequestUser(Option<ObjectId>);
+
+/// Note which user made a request, so that their ID is logged with it.
+pub fn set_request_user(request: &Request<'_>, user: ObjectId) {
+    request.local_cache(|| RequestUser(Some(user)));
+}
+
+/// A request ID passed on by a proxy is kept if it is short and plain, so that logs can be followed across both.
+fn incoming_request_id(request: &Request<'_>) -> Option<String> {
+    let id = request.headers().get_one(REQUEST_ID_HEADER)?;
+    let valid = !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LENGTH
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+
+    valid.then(|| id.to_string())
+}
+
+/// A fairing that gives each request an ID, sends it back in the `X-Request-Id` header and logs the request.
+/// Only the method, route, status, latency and the user's ID are logged, never paths, queries or bodies,
+/// since those can hold usernames and passwords.
+pub struct RequestLogger;
+
+#[rocket::async_trait]
+impl Fairing for RequestLogger {
+    fn info(&self) -> Info {
+        Info {
+            name: "Request logger",
+            kind: Kind::Request | Kind::Response,
+        }
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _data: &mut Data<'_>) {
+        let id = incoming_request_id(request).unwrap_or_else(|| ObjectId::new().to_hex());
+        request.local_cache(|| RequestLog {
+            id,
+            start: Instant::now(),
+        });
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let log = request.local_cache(|| RequestLog {
+            id: ObjectId::new().to_hex(),
+            start: Instant::now(),
+        });
+        response.set_raw_header(REQUEST_ID_HEADER, log.id.clone());
+
+        let user_id = request
+            .local_cache(|| RequestUser(None))
+            .0
+            .map(|user| user.to_hex());
+        let route = request
+            .route()
+            .map_or("unmatched", |route| route.uri.path());
+        let status = response.status().code;
+        let latency_ms = log.start.elapsed().as_secs_f64() * 1000.0;
+
+        macro_rules! log_request {
+            ($level:ident) => {
+                tracing::$level!(
+                    request_id = %log.id,
+                    method = %request.method(),
+                    route,
+                    status,
+                    latency_ms,
+                    user_id = user_id.as_deref(),
+                    "Handled request"
+                )
+            };
+        }
+        match status {
+            500.. => log_request!(error),
+            400..=499 => log_request!(warn),
+            _ => log_request!(info),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rocket::{http::Header, local::blocking::Client};
+
+    use super::*;
+
+    #[get("/")]
+    fn index() -> &'static str {
+        "Bread"
+    }
+
+    #[test]
+    fn requests_get_an_id() {
+        let rocket = rocket::build()
+            .mount("/", routes![index])
+            .attach(RequestLogger);
+        let client = Client::tracked(rocket).unwrap();
+
+        let response = client.get("/").dispatch();
+        let id = response.headers().get_one(REQUEST_ID_HEADER).unwrap();
+        assert_eq!(id.len(), 24);
+
+        // An ID from a proxy is kept, unless it could be used to mess up the logs.
+        let response = client
+            .get("/")
+            .header(Header::new(REQUEST_ID_HEADER, "proxy-id.1"))
+            .dispatch();
+        assert_eq!(
+            response.headers().get_one(REQUEST_ID_HEADER),
+            Some("proxy-id.1")
+        );
+
+        let response = client
+            .get("/")
+            .header(Header::new(REQUEST_ID_HEADER, "forged\" user=\"admin"))
+            .dispatch();
+        assert_ne!(
+            response.headers().get_one(REQUEST_ID_HEADER),
+            Some("forged\" user=\"admin")
+        );
+    }
+}
@@ -1,31 +1,252 @@
-use database::DatabaseHandler;
-use rocket::{fs::FileServer, response::Redirect};
+use bread::{
+    api::{self, search::SearchLimiter, wellbeing::ActivityLimiter},
+    app,
+    classifier::{ClassifierConfig, PostClassifier},
+    config::BreadConfig,
+    database::DatabaseHandler,
+    logging::RequestLogger,
+    metrics::{Metrics, RequestMetrics},
+    startup::{self, StartupError},
+    sweeper,
+};
+use clap::{Parser, Subcommand};
+use rocket::{fairing::AdHoc, fs::FileServer, response::Redirect, Build, Rocket};
 use rocket_dyn_templates::Template;
+use std::process::ExitCode;
 
 #[macro_use]
 extern crate rocket;
-mod api;
-mod app;
-mod database;
-mod models;
+
+/// Serve Bread. Reads its configuration from `Rocket.toml`, `BREAD_` environment variables and `MONGO_URI`.
+#[derive(Debug, Parser)]
+#[command(name = "bread", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Apply the pending database migrations and exit, instead of serving.
+    Migrate,
+}
 
 #[get("/")]
 fn index() -> Redirect {
     Redirect::to(uri!("/app"))
 }
 
-#[launch]
-fn rocket() -> _ {
-    let database_handler = match DatabaseHandler::create_connection() {
-        Ok(handler) => handler,
-        Err(e) => panic!("{}", e),
-    };
-    rocket::build()
+/// Mount all routes and catchers of Bread.
+/// The debug routes are only mounted when the "debug-routes" feature is enabled.
+fn mount_routes(rocket: Rocket<Build>) -> Rocket<Build> {
+    let rocket = rocket
         .mount("/", routes![index])
+        .mount("/", api::health::get_health_routes())
         .mount("/app", app::get_app_routes())
+        .register("/app", app::get_app_catchers())
+        .mount("/app/admin", app::admin::get_admin_app_routes())
         .mount("/api", api::get_api_routes())
-        .mount("/debug", api::debug::get_debug_routes())
-        .mount("/static", FileServer::from("./static"))
+        .mount("/api", api::reports::get_report_routes())
+        .mount("/api", api::reactions::get_reaction_routes())
+        .mount("/api", api::replies::get_reply_routes())
+        .mount("/api", api::conversations::get_conversation_routes())
+        .mount("/api", api::devices::get_device_routes())
+        .mount("/api", api::restrictions::get_restriction_routes())
+        .mount("/api", api::search::get_search_routes())
+        .mount("/api", api::wellbeing::get_wellbeing_routes())
+        .mount("/api/admin", api::admin::get_admin_routes())
+        .mount("/static", FileServer::from("./static"));
+
+    #[cfg(feature = "debug-routes")]
+    let rocket = rocket.mount("/debug", api::debug::get_debug_routes());
+
+    rocket
+}
+
+/// Serve `/metrics` on Bread's own port, unless `metrics_port` moves it to a server of its own.
+fn mount_metrics(rocket: Rocket<Build>, config: &BreadConfig) -> Rocket<Build> {
+    match config.metrics_port {
+        Some(_) => rocket,
+        None => rocket.mount("/", api::metrics::get_metrics_routes()),
+    }
+}
+
+/// Bring the database up to date before serving, or make sure it already is if `migrate_on_startup` is off.
+fn migrate_on_startup(db: &DatabaseHandler, config: &BreadConfig) -> Result<(), StartupError> {
+    if config.migrate_on_startup {
+        return db
+            .run_migrations()
+            .map(|_| ())
+            .map_err(StartupError::Migration);
+    }
+
+    match db
+        .pending_migrations()
+        .map_err(StartupError::Migration)?
+        .first()
+    {
+        Some(migration) => Err(StartupError::Migration(format!(
+            "Migration {} ({}) hasn't been applied, run `bread migrate` first!",
+            migration.version, migration.name
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Set up Bread, short of launching it.
+fn build_rocket() -> Result<Rocket<Build>, StartupError> {
+    let (figment, config) = startup::load_config()?;
+    let database_handler = startup::connect_database(&config)?;
+    migrate_on_startup(&database_handler, &config)?;
+    let rocket = rocket::custom(figment);
+    let image_storage = startup::open_image_storage(&config)?;
+    let rocket = mount_metrics(rocket, &config);
+
+    Ok(mount_routes(rocket)
+        .mount("/images", api::images::get_image_routes())
         .manage(database_handler)
+        .manage(image_storage)
+        .manage(config)
+        .manage(SearchLimiter::default())
+        .manage(ActivityLimiter::default())
+        .manage(Metrics::default())
+        .attach(RequestMetrics)
+        .attach(RequestLogger)
         .attach(Template::fairing())
+        .attach(sweeper::expired_post_sweeper())
+        .attach(AdHoc::try_on_ignite("Content classifier", |rocket| async {
+            let config = match rocket
+                .figment()
+                .extract_inner::<ClassifierConfig>("classifier")
+            {
+                Ok(config) => config,
+                Err(err) if err.missing() => {
+                    warn!("No classifier configured, posts will not be checked.");
+                    ClassifierConfig::default()
+                }
+                Err(err) => {
+                    error!("Invalid classifier configuration: {}", err);
+                    return Err(rocket);
+                }
+            };
+            Ok(rocket.manage(PostClassifier::from_config(config)))
+        }))
+        .attach(AdHoc::try_on_ignite("Post search", |rocket| async {
+            let (db, language) = match (
+                rocket.state::<DatabaseHandler>(),
+                rocket.state::<BreadConfig>(),
+            ) {
+                (Some(db), Some(config)) => (db, &config.search_language),
+                _ => return Err(rocket),
+            };
+            match db.create_post_text_index(language) {
+                Ok(()) => Ok(rocket),
+                Err(err) => {
+                    error!("Could not make posts searchable in {}: {}", language, err);
+                    Err(rocket)
+                }
+            }
+        })))
+}
+
+/// The server that `/metrics` is served on if `metrics_port` is set. It shares the metrics of Bread's own server.
+fn build_metrics_server(rocket: &Rocket<Build>) -> Option<Rocket<Build>> {
+    let port = rocket.state::<BreadConfig>()?.metrics_port?;
+    let metrics = rocket.state::<Metrics>()?.clone();
+
+    Some(
+        rocket::custom(rocket.figment().clone().merge(("port", port)))
+            .mount("/", api::metrics::get_metrics_routes())
+            .manage(metrics),
+    )
+}
+
+fn main() -> ExitCode {
+    if let Some(Command::Migrate) = Cli::parse().command {
+        return match startup::migrate() {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(err) => {
+                error!("{}", err);
+                err.exit_code()
+            }
+        };
+    }
+
+    let rocket = match build_rocket() {
+        Ok(rocket) => rocket,
+        Err(err) => {
+            error!("Bread could not start. {}", err);
+            return err.exit_code();
+        }
+    };
+    let metrics_server = build_metrics_server(&rocket);
+
+    let result = rocket::execute(async move {
+        // Bread keeps running without its metrics, since they aren't needed to serve users.
+        if let Some(metrics_server) = metrics_server {
+            rocket::tokio::spawn(async move {
+                if let Err(err) = metrics_server.launch().await {
+                    error!("The metrics server stopped, {}.", err.pretty_print());
+                }
+            });
+        }
+
+        rocket.launch().await
+    });
+
+    match result {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(err) => {
+            error!("Bread could not start, {}.", err.pretty_print());
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::CommandFactory;
+    use rocket::{http::Status, local::blocking::Client};
+
+    use super::*;
+
+    /// Builds without the "debug-routes" feature, such as release builds, must not expose `/debug`.
+    #[test]
+    fn cli_is_valid() {
+        Cli::command().debug_assert();
+
+        let cli = Cli::try_parse_from(["bread", "migrate"]).unwrap();
+        assert!(matches!(cli.command, Some(Command::Migrate)));
+        assert!(Cli::try_parse_from(["bread"]).unwrap().command.is_none());
+        assert!(Cli::try_parse_from(["bread", "serve"]).is_err());
+    }
+
+    #[test]
+    fn debug_routes_are_only_mounted_with_feature() {
+        let rocket = mount_routes(rocket::build());
+        let has_debug_routes = rocket
+            .routes()
+            .any(|route| route.uri.base().starts_with("/debug"));
+
+        assert_eq!(has_debug_routes, cfg!(feature = "debug-routes"));
+    }
+
+    /// `/metrics` is served on Bread's own port by default, and only on `metrics_port` once that is set.
+    #[test]
+    fn metrics_move_to_metrics_port() {
+        let status = |config: BreadConfig| {
+            let rocket = mount_metrics(rocket::build(), &config).manage(Metrics::default());
+            let client = Client::untracked(rocket).unwrap();
+            let response = client.get("/metrics").dispatch();
+            response.status()
+        };
+
+        assert_eq!(status(BreadConfig::default()), Status::Ok);
+
+        let separate = BreadConfig {
+            metrics_port: Some(9100),
+            ..Default::default()
+        };
+        assert_eq!(status(separate), Status::NotFound);
+    }
 }
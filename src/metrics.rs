@@ -0,0 +1,299 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use rocket::{
+    fairing::{Fairing, Info, Kind},
+    Data, Request, Response,
+};
+
+/// The upper bounds of the buckets that request latencies are sorted into, in seconds.
+const LATENCY_BUCKETS: [f64; 10] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0];
+
+/// Things that happen in Bread that are worth counting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    Registration,
+    LoginSucceeded,
+    LoginFailed,
+    PostCreated,
+    RandomPostServed,
+}
+
+impl Event {
+    const ALL: [Event; 5] = [
+        Event::Registration,
+        Event::LoginSucceeded,
+        Event::LoginFailed,
+        Event::PostCreated,
+        Event::RandomPostServed,
+    ];
+
+    /// The name of the event's counter, and what it counts. Logins share a counter, told apart by a label.
+    fn counter(self) -> (&'static str, &'static str) {
+        match self {
+            Event::Registration => ("bread_registrations_total", "Users that have registered."),
+            Event::LoginSucceeded | Event::LoginFailed => (
+                "bread_logins_total",
+                "Login attempts, by whether the credentials were correct.",
+            ),
+            Event::PostCreated => (
+                "bread_posts_created_total",
+                "Posts that have been published.",
+            ),
+            Event::RandomPostServed => (
+                "bread_random_posts_served_total",
+                "Posts that have been shown on the random page.",
+            ),
+        }
+    }
+
+    /// The labels that tell events that share a counter apart.
+    fn labels(self) -> &'static str {
+        match self {
+            Event::LoginSucceeded => "{result=\"succeeded\"}",
+            Event::LoginFailed => "{result=\"failed\"}",
+            _ => "",
+        }
+    }
+}
+
+/// The requests to one route, for one method.
+#[derive(Default)]
+struct RouteStats {
+    /// How many requests got each response status.
+    statuses: BTreeMap<u16, u64>,
+    /// How many requests fell into each of `LATENCY_BUCKETS`, not counting those in earlier buckets.
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    count: u64,
+    seconds: f64,
+}
+
+#[derive(Default)]
+struct MetricsInner {
+    /// Keyed by method and route, e.g. ("GET", "/app/random"), so that they are listed in a stable order.
+    requests: Mutex<BTreeMap<(String, String), RouteStats>>,
+    events: [AtomicU64; Event::ALL.len()],
+}
+
+/// Counts requests and events while Bread is running, to be scraped by Prometheus on `/metrics`.
+/// The counts are only kept in memory, so they start over when Bread restarts, which Prometheus expects.
+/// Cloning it is cheap, the clones share the same counts.
+#[derive(Clone, Default)]
+pub struct Metrics(Arc<MetricsInner>);
+
+impl Metrics {
+    /// Count an event.
+    pub fn count(&self, event: Event) {
+        self.0.events[event as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record a handled request. `route` is the route's path with its parameters, never the requested path,
+    /// so that there is only one series per route.
+    fn record_request(&self, method: &str, route: &str, status: u16, latency: Duration) {
+        // A poisoned lock only means that another thread panicked while holding it, the counts are still usable.
+        let mut requests = self
+            .0
+            .requests
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let stats = requests
+            .entry((method.to_string(), route.to_string()))
+            .or_default();
+
+        *stats.statuses.entry(status).or_default() += 1;
+        let seconds = latency.as_secs_f64();
+        if let Some(bucket) = LATENCY_BUCKETS.iter().position(|&le| seconds <= le) {
+            stats.buckets[bucket] += 1;
+        }
+        stats.count += 1;
+        stats.seconds += seconds;
+    }
+
+    /// Write all metrics in Prometheus' text format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        // Writing to a String can't fail.
+        let _ = self.write_requests(&mut out);
+        let _ = self.write_events(&mut out);
+        out
+    }
+
+    fn write_requests(&self, out: &mut String) -> std::fmt::Result {
+        let requests = self
+            .0
+            .requests
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        writeln!(
+            out,
+            "# HELP bread_http_requests_total HTTP requests, by route and response status."
+        )?;
+        writeln!(out, "# TYPE bread_http_requests_total counter")?;
+        for ((method, route), stats) in requests.iter() {
+            for (status, count) in &stats.statuses {
+                writeln!(
+                    out,
+                    "bread_http_requests_total{{method=\"{}\",route=\"{}\",status=\"{}\"}} {}",
+                    method,
+                    escape(route),
+                    status,
+                    count
+                )?;
+            }
+        }
+
+        writeln!(out, "# HELP bread_http_request_duration_seconds How long requests took to handle, by route.")?;
+        writeln!(out, "# TYPE bread_http_request_duration_seconds histogram")?;
+        for ((method, route), stats) in requests.iter() {
+            let labels = format!("method=\"{}\",route=\"{}\"", method, escape(route));
+            let mut cumulative = 0;
+            for (le, count) in LATENCY_BUCKETS.iter().zip(stats.buckets) {
+                cumulative += count;
+                writeln!(
+                    out,
+                    "bread_http_request_duration_seconds_bucket{{{},le=\"{}\"}} {}",
+                    labels, le, cumulative
+                )?;
+            }
+            writeln!(
+                out,
+                "bread_http_request_duration_seconds_bucket{{{},le=\"+Inf\"}} {}",
+                labels, stats.count
+            )?;
+            writeln!(
+                out,
+                "bread_http_request_duration_seconds_sum{{{}}} {}",
+                labels, stats.seconds
+            )?;
+            writeln!(
+                out,
+                "bread_http_request_duration_seconds_count{{{}}} {}",
+                labels, stats.count
+            )?;
+        }
+
+        Ok(())
+    }
+
+    fn write_events(&self, out: &mut String) -> std::fmt::Result {
+        let mut last_name = "";
+        for event in Event::ALL {
+            let (name, help) = event.counter();
+            if name != last_name {
+                writeln!(out, "# HELP {} {}", name, help)?;
+                writeln!(out, "# TYPE {} counter", name)?;
+                last_name = name;
+            }
+            writeln!(
+                out,
+                "{}{} {}",
+                name,
+                event.labels(),
+                self.0.events[event as usize].load(Ordering::Relaxed)
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Escape a label value, see https://prometheus.io/docs/instrumenting/exposition_formats/.
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// When Rocket started handling a request.
+struct RequestStart(Instant);
+
+/// A fairing that records how many requests each route gets and how long they take.
+/// Requests that don't match a route are counted together, so that made up paths can't add series.
+pub struct RequestMetrics;
+
+#[rocket::async_trait]
+impl Fairing for RequestMetrics {
+    fn info(&self) -> Info {
+        Info {
+            name: "Request metrics",
+            kind: Kind::Request | Kind::Response,
+        }
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _data: &mut Data<'_>) {
+        request.local_cache(|| RequestStart(Instant::now()));
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let metrics = match request.rocket().state::<Metrics>() {
+            Some(metrics) => metrics,
+            None => return,
+        };
+        let start = request.local_cache(|| RequestStart(Instant::now()));
+        let route = request
+            .route()
+            .map_or("unmatched", |route| route.uri.path());
+
+        metrics.record_request(
+            request.method().as_str(),
+            route,
+            response.status().code,
+            start.0.elapsed(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn metrics_are_rendered_for_prometheus() {
+        let metrics = Metrics::default();
+        metrics.record_request("GET", "/app/random", 200, Duration::from_millis(20));
+        metrics.record_request("GET", "/app/random", 200, Duration::from_secs(10));
+        metrics.record_request("GET", "/app/random", 500, Duration::from_millis(1));
+        metrics.count(Event::LoginFailed);
+        metrics.count(Event::LoginFailed);
+
+        let rendered = metrics.render();
+        let has_line = |line: &str| rendered.lines().any(|rendered| rendered == line);
+
+        assert!(has_line(
+            "bread_http_requests_total{method=\"GET\",route=\"/app/random\",status=\"200\"} 2"
+        ));
+        assert!(has_line(
+            "bread_http_requests_total{method=\"GET\",route=\"/app/random\",status=\"500\"} 1"
+        ));
+        // Buckets count every request at or below their bound, and only +Inf counts the slowest.
+        assert!(has_line(
+            "bread_http_request_duration_seconds_bucket{method=\"GET\",route=\"/app/random\",le=\"0.005\"} 1"
+        ));
+        assert!(has_line(
+            "bread_http_request_duration_seconds_bucket{method=\"GET\",route=\"/app/random\",le=\"0.025\"} 2"
+        ));
+        assert!(has_line(
+            "bread_http_request_duration_seconds_bucket{method=\"GET\",route=\"/app/random\",le=\"5\"} 2"
+        ));
+        assert!(has_line(
+            "bread_http_request_duration_seconds_bucket{method=\"GET\",route=\"/app/random\",le=\"+Inf\"} 3"
+        ));
+        assert!(has_line("bread_logins_total{result=\"failed\"} 2"));
+        assert!(has_line("bread_logins_total{result=\"succeeded\"} 0"));
+        assert_eq!(
+            rendered
+                .matches("# TYPE bread_logins_total counter")
+                .count(),
+            1
+        );
+    }
+}
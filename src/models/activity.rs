@@ -0,0 +1,168 @@
+use mongodb::bson::{oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+
+const MINUTE: i64 = 60 * 1000;
+const DAY: i64 = 24 * 60 * MINUTE;
+
+/// A moment in a user's own time zone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalTime {
+    /// The user's date, like "2024-05-31".
+    pub day: String,
+    /// How many minutes of the day have passed, from 0 to 1439.
+    pub minute: u16,
+}
+
+impl LocalTime {
+    /// The time at `instant` for a user whose clock is `utc_offset_minutes` ahead of UTC.
+    pub fn at(instant: DateTime, utc_offset_minutes: i32) -> Result<Self, String> {
+        let local = instant.saturating_add_millis(i64::from(utc_offset_minutes) * MINUTE);
+        let day = local
+            .try_to_rfc3339_string()
+            .map_err(|err| err.to_string())?
+            .chars()
+            .take(10)
+            .collect();
+
+        Ok(LocalTime {
+            day,
+            minute: (local.timestamp_millis().rem_euclid(DAY) / MINUTE) as u16,
+        })
+    }
+
+    /// The dates of the last `count` days for a user, oldest first and ending with today.
+    pub fn last_days(
+        now: DateTime,
+        utc_offset_minutes: i32,
+        count: i64,
+    ) -> Result<Vec<String>, String> {
+        (0..count)
+            .rev()
+            .map(|days_ago| {
+                LocalTime::at(
+                    now.saturating_add_millis(-days_ago * DAY),
+                    utc_offset_minutes,
+                )
+                .map(|time| time.day)
+            })
+            .collect()
+    }
+
+    /// The hour of the day, from 0 to 23.
+    pub fn hour(&self) -> u8 {
+        (self.minute / 60) as u8
+    }
+}
+
+/// Activity closer together than this belongs to the same visit, and the time in between counts as use.
+pub const VISIT_GAP_MILLIS: i64 = 5 * MINUTE;
+
+/// How much a visit of a single request counts as.
+pub const SHORT_VISIT_MILLIS: i64 = MINUTE;
+
+/**
+ * How long a user used Bread on a day, in their own time zone.
+ * This is what daily time budgets and weekly summaries are worked out from.
+ * The daily quotas are kept here too, but on the document of the day in UTC, see `api::wellbeing::take_quota`.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActivityDay {
+    #[serde(skip_serializing_if = "Option::is_none", rename = "_id")]
+    pub id: Option<ObjectId>,
+    pub user: ObjectId,
+    pub day: String,
+    /// How long the user's visits on the day have lasted, see `DatabaseHandler::record_activity`.
+    #[serde(default)]
+    pub active_millis: i64,
+    /// When the user was last seen. Activity is deleted a while after it was last added to.
+    pub last_active_at: DateTime,
+    /// How many posts the user has created on the day in UTC with this date.
+    #[serde(default)]
+    pub posts: u32,
+    /// How many random posts the user has been shown on the day in UTC with this date.
+    #[serde(default)]
+    pub random_posts: u32,
+}
+
+/// Something a user may only do a limited number of times per day, see `BreadConfig`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DailyQuota {
+    Posts,
+    RandomPosts,
+}
+
+impl ActivityDay {
+    /// How many whole minutes the user was active on the day.
+    pub fn active_minutes(&self) -> usize {
+        (self.active_millis / MINUTE).max(0) as usize
+    }
+}
+
+impl DailyQuota {
+    /// The field of `ActivityDay` that counts how much of the quota has been used.
+    pub fn field(&self) -> &'static str {
+        match self {
+            DailyQuota::Posts => "posts",
+            DailyQuota::RandomPosts => "random_posts",
+        }
+    }
+
+    /// How much of the quota has been used on a day.
+    pub fn used(&self, activity: &ActivityDay) -> u32 {
+        match self {
+            DailyQuota::Posts => activity.posts,
+            DailyQuota::RandomPosts => activity.random_posts,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The offset moves the time into the user's own day.
+    #[test]
+    fn local_time_uses_offset() {
+        // 2024-05-31 23:30 UTC.
+        let instant = DateTime::parse_rfc3339_str("2024-05-31T23:30:00Z").unwrap();
+
+        let utc = LocalTime::at(instant, 0).unwrap();
+        assert_eq!(utc.day, "2024-05-31");
+        assert_eq!(utc.minute, 23 * 60 + 30);
+        assert_eq!(utc.hour(), 23);
+
+        let stockholm = LocalTime::at(instant, 120).unwrap();
+        assert_eq!(stockholm.day, "2024-06-01");
+        assert_eq!(stockholm.hour(), 1);
+
+        let new_york = LocalTime::at(instant, -240).unwrap();
+        assert_eq!(new_york.day, "2024-05-31");
+        assert_eq!(new_york.hour(), 19);
+    }
+
+    /// Days from before quotas were counted haven't used any of them.
+    #[test]
+    fn quotas_default_to_unused() {
+        let activity: ActivityDay = mongodb::bson::from_document(mongodb::bson::doc! {
+            "user": ObjectId::new(),
+            "day": "2024-05-31",
+            "active_millis": 2 * MINUTE,
+            "last_active_at": DateTime::now(),
+        })
+        .unwrap();
+
+        assert_eq!(activity.active_minutes(), 2);
+        assert_eq!(DailyQuota::Posts.used(&activity), 0);
+        assert_eq!(DailyQuota::RandomPosts.used(&activity), 0);
+    }
+
+    #[test]
+    fn last_days_end_today() {
+        let instant = DateTime::parse_rfc3339_str("2024-03-02T12:00:00Z").unwrap();
+
+        assert_eq!(
+            LocalTime::last_days(instant, 0, 3).unwrap(),
+            vec!["2024-02-29", "2024-03-01", "2024-03-02"]
+        );
+    }
+}
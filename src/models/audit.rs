@@ -0,0 +1,53 @@
+use mongodb::bson::{oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+
+/// Something a moderator or admin did that should be kept track of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ModerationAction {
+    SuspendUser,
+    UnsuspendUser,
+    RemovePost,
+    DismissReports,
+    ChangeRole,
+    DeleteUser,
+}
+
+/**
+ * An entry in the audit trail, recording who did what to which user or post, and when.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    #[serde(skip_serializing_if = "Option::is_none", rename = "_id")]
+    pub id: Option<ObjectId>,
+    /// Who did it, or `None` if it was done with `bread-admin`.
+    pub moderator: Option<ObjectId>,
+    pub action: ModerationAction,
+    pub target: ObjectId,
+    pub timestamp: DateTime,
+}
+
+impl AuditEntry {
+    /// Create a new audit entry that happened right now.
+    /// This does not save the entry to the database!
+    pub fn create(moderator: ObjectId, action: ModerationAction, target: ObjectId) -> Self {
+        AuditEntry {
+            id: None,
+            moderator: Some(moderator),
+            action,
+            target,
+            timestamp: DateTime::now(),
+        }
+    }
+
+    /// Create a new audit entry for something done with `bread-admin` right now.
+    /// This does not save the entry to the database!
+    pub fn from_admin_tool(action: ModerationAction, target: ObjectId) -> Self {
+        AuditEntry {
+            id: None,
+            moderator: None,
+            action,
+            target,
+            timestamp: DateTime::now(),
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use mongodb::bson::{oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+
+/**
+ * A one-to-one conversation between two users.
+ * The participants are stored in order, so that each pair of users has exactly one conversation.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Conversation {
+    #[serde(skip_serializing_if = "Option::is_none", rename = "_id")]
+    pub id: Option<ObjectId>,
+    pub first: ObjectId,
+    pub second: ObjectId,
+    pub created_at: DateTime,
+    pub last_message_at: Option<DateTime>,
+    pub last_sender: Option<ObjectId>,
+    /// When `first` last read the conversation.
+    pub first_read_at: Option<DateTime>,
+    /// When `second` last read the conversation.
+    pub second_read_at: Option<DateTime>,
+}
+
+impl Conversation {
+    /// Create a new conversation without any messages.
+    /// This does not save the conversation to the database!
+    pub fn create(a: ObjectId, b: ObjectId) -> Self {
+        let (first, second) = Self::order(a, b);
+
+        Conversation {
+            id: None,
+            first,
+            second,
+            created_at: DateTime::now(),
+            last_message_at: None,
+            last_sender: None,
+            first_read_at: None,
+            second_read_at: None,
+        }
+    }
+
+    /// Order two participants the way they are stored.
+    pub fn order(a: ObjectId, b: ObjectId) -> (ObjectId, ObjectId) {
+        if a < b {
+            (a, b)
+        } else {
+            (b, a)
+        }
+    }
+
+    /// Check if a user takes part in the conversation.
+    pub fn has_participant(&self, user: &ObjectId) -> bool {
+        &self.first == user || &self.second == user
+    }
+
+    /// Get the participant that isn't `user`.
+    pub fn other(&self, user: &ObjectId) -> ObjectId {
+        if &self.first == user {
+            self.second
+        } else {
+            self.first
+        }
+    }
+
+    /// The name of the field holding when `user` last read the conversation.
+    pub fn read_at_field(&self, user: &ObjectId) -> &'static str {
+        if &self.first == user {
+            "first_read_at"
+        } else {
+            "second_read_at"
+        }
+    }
+
+    /// When `user` last read the conversation.
+    pub fn read_at(&self, user: &ObjectId) -> Option<DateTime> {
+        if &self.first == user {
+            self.first_read_at
+        } else {
+            self.second_read_at
+        }
+    }
+
+    /// Check if the other participant has sent a message that `user` hasn't read yet.
+    pub fn is_unread_by(&self, user: &ObjectId) -> bool {
+        match (self.last_message_at, self.last_sender) {
+            (Some(last_message_at), Some(last_sender)) if &last_sender != user => self
+                .read_at(user)
+                .is_none_or(|read_at| read_at < last_message_at),
+            _ => false,
+        }
+    }
+}
+
+/**
+ * A copy of a message, encrypted for one device.
+ * The ciphertext is base64 encoded and is never decrypted by the server.
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, FromForm)]
+pub struct Envelope {
+    pub device: String,
+    pub ciphertext: String,
+}
+
+/**
+ * A message in a conversation.
+ * The server only knows who sent it and when, the content is only in the encrypted envelopes.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Message {
+    #[serde(skip_serializing_if = "Option::is_none", rename = "_id")]
+    pub id: Option<ObjectId>,
+    pub conversation: ObjectId,
+    pub sender: ObjectId,
+    pub envelopes: Vec<Envelope>,
+    pub created_at: DateTime,
+}
+
+impl Message {
+    /// Create a new message.
+    /// This does not save the message to the database!
+    pub fn create(conversation: ObjectId, sender: ObjectId, envelopes: Vec<Envelope>) -> Self {
+        Message {
+            id: None,
+            conversation,
+            sender,
+            envelopes,
+            created_at: DateTime::now(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The same two users should always end up in the same order.
+    #[test]
+    fn participants_are_ordered() {
+        let (a, b) = (ObjectId::new(), ObjectId::new());
+        let conversation = Conversation::create(b, a);
+
+        assert_eq!(Conversation::order(a, b), Conversation::order(b, a));
+        assert_eq!(
+            (conversation.first, conversation.second),
+            Conversation::order(a, b)
+        );
+        assert_eq!(conversation.other(&a), b);
+        assert!(!conversation.has_participant(&ObjectId::new()));
+    }
+
+    /// A conversation is unread until the recipient has read it after the last message.
+    #[test]
+    fn unread_conversation() {
+        let (a, b) = (ObjectId::new(), ObjectId::new());
+        let mut conversation = Conversation::create(a, b);
+        assert!(!conversation.is_unread_by(&b));
+
+        conversation.last_message_at = Some(DateTime::from_millis(1000));
+        conversation.last_sender = Some(a);
+        assert!(conversation.is_unread_by(&b));
+        assert!(!conversation.is_unread_by(&a));
+
+        if conversation.first == b {
+            conversation.first_read_at = Some(DateTime::from_millis(2000));
+        } else {
+            conversation.second_read_at = Some(DateTime::from_millis(2000));
+        }
+        assert!(!conversation.is_unread_by(&b));
+    }
+}
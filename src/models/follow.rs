@@ -0,0 +1,38 @@
+use mongodb::bson::{oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+
+/// Follows of private accounts have to be accepted before they count.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FollowStatus {
+    Pending,
+    #[default]
+    Accepted,
+}
+
+/**
+ * A follow means that `follower` sees the posts of `followee` in their friends feed.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Follow {
+    #[serde(skip_serializing_if = "Option::is_none", rename = "_id")]
+    pub id: Option<ObjectId>,
+    pub follower: ObjectId,
+    pub followee: ObjectId,
+    pub created_at: DateTime,
+    #[serde(default)]
+    pub status: FollowStatus,
+}
+
+impl Follow {
+    /// Create a new follow.
+    /// This does not save the follow to the database!
+    pub fn create(follower: ObjectId, followee: ObjectId, status: FollowStatus) -> Self {
+        Follow {
+            id: None,
+            follower,
+            followee,
+            created_at: DateTime::now(),
+            status,
+        }
+    }
+}
@@ -0,0 +1,13 @@
+use mongodb::bson::DateTime;
+use serde::{Deserialize, Serialize};
+
+/**
+ * A migration that has been applied to the database, so that it isn't applied again.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppliedMigration {
+    #[serde(rename = "_id")]
+    pub version: i32,
+    pub name: String,
+    pub applied_at: DateTime,
+}
@@ -1,2 +1,11 @@
+pub mod activity;
+pub mod audit;
+pub mod conversation;
+pub mod follow;
+pub mod migration;
 pub mod post;
+pub mod reaction;
+pub mod reply;
+pub mod report;
+pub mod restriction;
 pub mod user;
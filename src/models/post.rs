@@ -1,30 +1,229 @@
-use mongodb::bson::oid::ObjectId;
+use mongodb::bson::{oid::ObjectId, DateTime};
 use serde::{Deserialize, Serialize};
 
-/// Placeholder type for holding an image.
-type Image = ();
+/// The file name of an image in the image storage.
+type Image = String;
+
+/// How long a post lives before it is deleted.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, FromFormField)]
+pub enum PostLifetime {
+    #[field(value = "day")]
+    Day,
+    #[field(value = "week")]
+    Week,
+    #[default]
+    #[field(value = "forever")]
+    Forever,
+}
+
+impl PostLifetime {
+    /// When a post created at `created_at` with this lifetime expires, if ever.
+    pub fn expires_at(self, created_at: DateTime) -> Option<DateTime> {
+        const DAY: i64 = 24 * 60 * 60 * 1000;
+
+        match self {
+            PostLifetime::Day => Some(created_at.saturating_add_millis(DAY)),
+            PostLifetime::Week => Some(created_at.saturating_add_millis(7 * DAY)),
+            PostLifetime::Forever => None,
+        }
+    }
+}
+
+/// A warning that a post is about something that may be hard to read.
+/// Posts with a warning are shown collapsed behind it, unless the reader has chosen otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, FromFormField)]
+#[serde(rename_all = "kebab-case")]
+pub enum ContentWarning {
+    #[field(value = "self-harm")]
+    SelfHarm,
+    #[field(value = "suicide")]
+    Suicide,
+    #[field(value = "eating-disorders")]
+    EatingDisorders,
+    #[field(value = "substance-use")]
+    SubstanceUse,
+    #[field(value = "abuse")]
+    Abuse,
+    #[field(value = "grief")]
+    Grief,
+    #[field(value = "other")]
+    Other,
+}
+
+impl ContentWarning {
+    /// All warnings, in the order they are shown.
+    pub const ALL: [ContentWarning; 7] = [
+        ContentWarning::SelfHarm,
+        ContentWarning::Suicide,
+        ContentWarning::EatingDisorders,
+        ContentWarning::SubstanceUse,
+        ContentWarning::Abuse,
+        ContentWarning::Grief,
+        ContentWarning::Other,
+    ];
+}
+
+/// How many topics a post can have. Hashtags after that many are left as plain text.
+pub const MAX_TOPICS: usize = 10;
+
+/// Hashtags longer than this aren't topics.
+const MAX_TOPIC_LENGTH: usize = 50;
+
+/// Hashtags are made of letters, digits and underscores.
+fn is_topic_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Turn a hashtag like "#Baking" into the topic "baking".
+/// Returns `None` if it can't be a topic, e.g. if it is too long or has no letters in it.
+pub fn normalize_topic(hashtag: &str) -> Option<String> {
+    let topic = hashtag.strip_prefix('#').unwrap_or(hashtag).to_lowercase();
+    let is_valid = topic.chars().count() <= MAX_TOPIC_LENGTH
+        && topic.chars().all(is_topic_char)
+        && topic.chars().any(char::is_alphabetic);
+
+    is_valid.then_some(topic)
+}
+
+/// Find the topics of a post from the hashtags in its content, in the order they first appear.
+/// A hashtag starts with a '#' that isn't in the middle of a word, so "C#" isn't a hashtag.
+pub fn parse_topics(content: &str) -> Vec<String> {
+    let mut topics: Vec<String> = vec![];
+    let mut previous = None;
+
+    for (i, c) in content.char_indices() {
+        let starts_hashtag =
+            c == '#' && !previous.is_some_and(|p: char| is_topic_char(p) || p == '#');
+        previous = Some(c);
+        if !starts_hashtag {
+            continue;
+        }
+
+        let rest = &content[i + 1..];
+        let end = rest.find(|c| !is_topic_char(c)).unwrap_or(rest.len());
+        if let Some(topic) = normalize_topic(&rest[..end]) {
+            if !topics.contains(&topic) {
+                topics.push(topic);
+            }
+        }
+
+        if topics.len() == MAX_TOPICS {
+            break;
+        }
+    }
+
+    topics
+}
+
+/// Posts that were saved before posts had timestamps are treated as being from the Unix epoch.
+fn unix_epoch() -> DateTime {
+    DateTime::from_millis(0)
+}
 
 /**
  * A post holds the author and optional content and/or image.
  */
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Post {
     #[serde(skip_serializing_if = "Option::is_none", rename = "_id")]
     pub id: Option<ObjectId>,
     pub author: ObjectId,
     pub content: Option<String>,
     pub image: Option<Image>,
+    #[serde(default = "unix_epoch")]
+    pub created_at: DateTime,
+    #[serde(default)]
+    pub edited_at: Option<DateTime>,
+    /// Expired posts are never shown, and are deleted in the background.
+    #[serde(default)]
+    pub expires_at: Option<DateTime>,
+    /// Hidden posts are left out of random discovery until a moderator has looked at them.
+    #[serde(default)]
+    pub hidden: bool,
+    /// Set for posts that suggest the author is in crisis, so that strangers don't stumble upon them.
+    #[serde(default)]
+    pub excluded_from_discovery: bool,
+    /// Set by the author if the post may be hard to read.
+    #[serde(default)]
+    pub content_warning: Option<ContentWarning>,
+    /// The hashtags in the content, normalized by `normalize_topic`.
+    #[serde(default)]
+    pub topics: Vec<String>,
+    /// A copy of whether the author's account is private, so that discovery doesn't have to look up every private account.
+    #[serde(default)]
+    pub author_private: bool,
 }
 
 impl Post {
-    /// Create a new post from an author and content.
+    /// Create a new post from an author and content. The topics are taken from the content's hashtags.
     /// This does not save the post to the database!
     pub fn create(author: ObjectId, content: Option<String>, image: Option<Image>) -> Self {
+        let topics = content.as_deref().map(parse_topics).unwrap_or_default();
+
         Post {
             id: None,
             author,
             content,
             image,
+            created_at: DateTime::now(),
+            edited_at: None,
+            expires_at: None,
+            hidden: false,
+            excluded_from_discovery: false,
+            content_warning: None,
+            topics,
+            author_private: false,
         }
     }
+
+    /// Check if the post has expired. Expired posts may still be in the database until they have been swept.
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at <= DateTime::now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Make sure the lifetimes expire when they should.
+    #[test]
+    fn post_lifetime_expiry() {
+        let created_at = DateTime::from_millis(0);
+
+        assert_eq!(
+            PostLifetime::Day.expires_at(created_at),
+            Some(DateTime::from_millis(86_400_000))
+        );
+        assert_eq!(
+            PostLifetime::Week.expires_at(created_at),
+            Some(DateTime::from_millis(604_800_000))
+        );
+        assert_eq!(PostLifetime::Forever.expires_at(created_at), None);
+    }
+
+    #[test]
+    fn hashtags_become_topics() {
+        assert_eq!(
+            parse_topics("Fresh #Sourdough today! #baking #sourdough, #bröd"),
+            vec!["sourdough", "baking", "bröd"]
+        );
+        assert_eq!(parse_topics("#a_b.c"), vec!["a_b"]);
+    }
+
+    #[test]
+    fn not_every_hash_is_a_hashtag() {
+        assert!(parse_topics("I write C# and my#word, ##double, # alone, #123").is_empty());
+        assert!(parse_topics(&format!("#{}", "a".repeat(MAX_TOPIC_LENGTH + 1))).is_empty());
+    }
+
+    #[test]
+    fn posts_have_a_limited_number_of_topics() {
+        let content: Vec<String> = (0..MAX_TOPICS + 5)
+            .map(|i| format!("#topic{}", i))
+            .collect();
+
+        assert_eq!(parse_topics(&content.join(" ")).len(), MAX_TOPICS);
+    }
 }
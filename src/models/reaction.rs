@@ -0,0 +1,85 @@
+use mongodb::bson::{oid::ObjectId, DateTime};
+use rocket::request::FromParam;
+use serde::{Deserialize, Serialize};
+
+/// The supportive reactions that can be sent to a post.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReactionKind {
+    Hug,
+    Strength,
+    Solidarity,
+    Thanks,
+}
+
+impl ReactionKind {
+    /// All kinds, in the order they are shown.
+    pub const ALL: [ReactionKind; 4] = [
+        ReactionKind::Hug,
+        ReactionKind::Strength,
+        ReactionKind::Solidarity,
+        ReactionKind::Thanks,
+    ];
+
+    /// The name of the kind as used in URLs.
+    pub fn name(&self) -> &'static str {
+        match self {
+            ReactionKind::Hug => "hug",
+            ReactionKind::Strength => "strength",
+            ReactionKind::Solidarity => "solidarity",
+            ReactionKind::Thanks => "thanks",
+        }
+    }
+}
+
+impl<'a> FromParam<'a> for ReactionKind {
+    type Error = &'a str;
+
+    fn from_param(param: &'a str) -> Result<Self, Self::Error> {
+        ReactionKind::ALL
+            .into_iter()
+            .find(|kind| kind.name() == param)
+            .ok_or(param)
+    }
+}
+
+/**
+ * A reaction that `reactor` sent to a post.
+ * Only the author of the post can see its reactions, and each user can only have one reaction per post.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reaction {
+    #[serde(skip_serializing_if = "Option::is_none", rename = "_id")]
+    pub id: Option<ObjectId>,
+    pub post: ObjectId,
+    pub reactor: ObjectId,
+    pub kind: ReactionKind,
+    pub created_at: DateTime,
+}
+
+impl Reaction {
+    /// Create a new reaction.
+    /// This does not save the reaction to the database!
+    pub fn create(post: ObjectId, reactor: ObjectId, kind: ReactionKind) -> Self {
+        Reaction {
+            id: None,
+            post,
+            reactor,
+            kind,
+            created_at: DateTime::now(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reaction_kind_from_param() {
+        for kind in ReactionKind::ALL {
+            assert_eq!(ReactionKind::from_param(kind.name()), Ok(kind));
+        }
+        assert!(ReactionKind::from_param("like").is_err());
+    }
+}
@@ -0,0 +1,46 @@
+use mongodb::bson::{oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+
+/**
+ * A private reply to a post. Only `to`, the author of the post, can read it.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reply {
+    #[serde(skip_serializing_if = "Option::is_none", rename = "_id")]
+    pub id: Option<ObjectId>,
+    pub post: ObjectId,
+    pub from: ObjectId,
+    pub to: ObjectId,
+    pub content: String,
+    pub created_at: DateTime,
+    pub read: bool,
+}
+
+/**
+ * How many replies a user has sent in one hour, counted before each reply is saved so that the hourly limit holds.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HourlyReplies {
+    #[serde(skip_serializing_if = "Option::is_none", rename = "_id")]
+    pub id: Option<ObjectId>,
+    pub from: ObjectId,
+    /// When the hour started.
+    pub hour: DateTime,
+    pub sent: u32,
+}
+
+impl Reply {
+    /// Create a new, unread reply.
+    /// This does not save the reply to the database!
+    pub fn create(post: ObjectId, from: ObjectId, to: ObjectId, content: String) -> Self {
+        Reply {
+            id: None,
+            post,
+            from,
+            to,
+            content,
+            created_at: DateTime::now(),
+            read: false,
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use mongodb::bson::{oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+
+/// Why a post was reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, FromFormField)]
+pub enum ReportReason {
+    #[field(value = "self-harm")]
+    SelfHarm,
+    #[field(value = "harassment")]
+    Harassment,
+    #[field(value = "spam")]
+    Spam,
+    #[field(value = "other")]
+    Other,
+}
+
+/**
+ * A report holds the post that was reported, who reported it and why.
+ * Each user can only have one open report of a post.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Report {
+    #[serde(skip_serializing_if = "Option::is_none", rename = "_id")]
+    pub id: Option<ObjectId>,
+    pub post: ObjectId,
+    pub reporter: ObjectId,
+    pub reason: ReportReason,
+    pub created_at: DateTime,
+    pub resolved: bool,
+}
+
+impl Report {
+    /// Create a new, unresolved report.
+    /// This does not save the report to the database!
+    pub fn create(post: ObjectId, reporter: ObjectId, reason: ReportReason) -> Self {
+        Report {
+            id: None,
+            post,
+            reporter,
+            reason,
+            created_at: DateTime::now(),
+            resolved: false,
+        }
+    }
+}
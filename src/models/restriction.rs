@@ -0,0 +1,76 @@
+use mongodb::bson::{oid::ObjectId, DateTime};
+use serde::{Deserialize, Serialize};
+
+/// How a user has restricted another user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RestrictionKind {
+    /// Neither user can see or interact with the other.
+    Block,
+    /// The user silently stops seeing the other user's posts.
+    Mute,
+}
+
+/**
+ * A block or mute that `user` has put on `target`.
+ */
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Restriction {
+    #[serde(skip_serializing_if = "Option::is_none", rename = "_id")]
+    pub id: Option<ObjectId>,
+    pub user: ObjectId,
+    pub target: ObjectId,
+    pub kind: RestrictionKind,
+    pub created_at: DateTime,
+}
+
+impl Restriction {
+    /// Create a new restriction.
+    /// This does not save the restriction to the database!
+    pub fn create(user: ObjectId, target: ObjectId, kind: RestrictionKind) -> Self {
+        Restriction {
+            id: None,
+            user,
+            target,
+            kind,
+            created_at: DateTime::now(),
+        }
+    }
+
+    /// The user whose posts this restriction hides from `viewer`, if any.
+    /// Viewers don't see the users they have blocked or muted, nor the users who have blocked them.
+    /// Being muted hides nothing, so that the muted user can't tell.
+    pub fn hides_from(&self, viewer: &ObjectId) -> Option<ObjectId> {
+        if &self.user == viewer {
+            Some(self.target)
+        } else if &self.target == viewer && self.kind == RestrictionKind::Block {
+            Some(self.user)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocks_hide_both_ways_and_mutes_one_way() {
+        let (viewer, other) = (ObjectId::new(), ObjectId::new());
+
+        let blocked = Restriction::create(viewer, other, RestrictionKind::Block);
+        assert_eq!(blocked.hides_from(&viewer), Some(other));
+
+        let muted = Restriction::create(viewer, other, RestrictionKind::Mute);
+        assert_eq!(muted.hides_from(&viewer), Some(other));
+
+        let blocked_by = Restriction::create(other, viewer, RestrictionKind::Block);
+        assert_eq!(blocked_by.hides_from(&viewer), Some(other));
+
+        let muted_by = Restriction::create(other, viewer, RestrictionKind::Mute);
+        assert_eq!(muted_by.hides_from(&viewer), None);
+
+        let unrelated = Restriction::create(other, ObjectId::new(), RestrictionKind::Block);
+        assert_eq!(unrelated.hides_from(&viewer), None);
+    }
+}
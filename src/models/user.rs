@@ -1,39 +1,171 @@
 use argon2::{Config, ThreadMode, Variant, Version};
-use mongodb::bson::oid::ObjectId;
+use mongodb::bson::{oid::ObjectId, DateTime};
 use password_hash::rand_core::OsRng;
 use serde::{Deserialize, Serialize};
 
+use crate::config::Argon2Config;
+
+use super::post::ContentWarning;
+
 /// There are no "profile pictures" in Bread. Instead, each profile has a color.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, FromFormField)]
 pub enum ProfileColor {
+    #[field(value = "orange")]
     Orange,
+    #[field(value = "red")]
     Red,
+    #[field(value = "green")]
     Green,
+    #[field(value = "blue")]
     Blue,
+    #[field(value = "grey")]
     Grey,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// What a user is allowed to do in Bread.
+/// The roles are ordered, so that e.g. an admin can do everything a moderator can.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Role {
+    #[default]
+    User,
+    Moderator,
+    Admin,
+}
+
+/// How a user wants to see posts with a certain content warning.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, FromFormField)]
+#[serde(rename_all = "kebab-case")]
+pub enum WarningPreference {
+    /// Shown collapsed behind the warning.
+    #[default]
+    #[field(value = "collapse")]
+    Collapse,
+    /// Shown right away, with the warning above it.
+    #[field(value = "expand")]
+    Expand,
+    /// Never shown.
+    #[field(value = "hide")]
+    Hide,
+}
+
+/// Hours of the day during which a user wants to rest from Bread, e.g. from 22 to 7.
+/// The hours are in the user's own time zone, and the end hour isn't included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuietHours {
+    pub start: u8,
+    pub end: u8,
+}
+
+impl QuietHours {
+    /// Check if an hour of the day is a quiet hour. Quiet hours may wrap around midnight.
+    pub fn contains(&self, hour: u8) -> bool {
+        if self.start <= self.end {
+            self.start <= hour && hour < self.end
+        } else {
+            hour >= self.start || hour < self.end
+        }
+    }
+}
+
+/// How a user wants to limit their own use of Bread.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WellbeingPreferences {
+    /// How many minutes a day the user wants to spend in Bread, if they have set a budget.
+    pub daily_minutes: Option<u16>,
+    pub quiet_hours: Option<QuietHours>,
+    /// How many minutes the user's clock is ahead of UTC, to know when their days and quiet hours start.
+    pub utc_offset_minutes: i32,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserPreferences {
     pub prefers_darkmode: bool,
     pub profile_color: ProfileColor,
+    /// Whether the people this user talks to can see when the user has read their messages.
+    #[serde(default = "default_true")]
+    pub send_read_receipts: bool,
+    /// Private accounts have to accept their followers, and their posts are only shown to them.
+    #[serde(default)]
+    pub is_private: bool,
+    /// The topics this user wants random discovery narrowed down to, normalized like the topics of posts.
+    #[serde(default)]
+    pub topics: Vec<String>,
+    /// Posts with these warnings are shown expanded. Posts with other warnings are collapsed, unless they are hidden.
+    #[serde(default)]
+    pub expanded_warnings: Vec<ContentWarning>,
+    /// Posts with these warnings are never shown to this user.
+    #[serde(default)]
+    pub hidden_warnings: Vec<ContentWarning>,
+    #[serde(default)]
+    pub wellbeing: WellbeingPreferences,
+}
+
+impl UserPreferences {
+    /// How this user wants to see posts with a warning.
+    pub fn warning_preference(&self, warning: ContentWarning) -> WarningPreference {
+        if self.hidden_warnings.contains(&warning) {
+            WarningPreference::Hide
+        } else if self.expanded_warnings.contains(&warning) {
+            WarningPreference::Expand
+        } else {
+            WarningPreference::Collapse
+        }
+    }
+
+    /// Change how this user wants to see posts with a warning.
+    pub fn set_warning_preference(
+        &mut self,
+        warning: ContentWarning,
+        preference: WarningPreference,
+    ) {
+        self.expanded_warnings
+            .retain(|expanded| expanded != &warning);
+        self.hidden_warnings.retain(|hidden| hidden != &warning);
+
+        match preference {
+            WarningPreference::Collapse => {}
+            WarningPreference::Expand => self.expanded_warnings.push(warning),
+            WarningPreference::Hide => self.hidden_warnings.push(warning),
+        }
+    }
+}
+
+/// A device that a user reads their messages on.
+/// Messages are encrypted on the sender's device for the public key of each device, so that the server can't read them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Device {
+    /// An id that the client picks for the device.
+    pub id: String,
+    /// The device's public key, base64 encoded.
+    pub public_key: String,
+    pub added_at: DateTime,
 }
 
 ///A user in Bread.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct User {
     #[serde(skip_serializing_if = "Option::is_none", rename = "_id")]
     pub id: Option<ObjectId>,
     pub name: String,
     pub password: String,
     pub preferences: UserPreferences,
+    #[serde(default)]
+    pub role: Role,
+    #[serde(default)]
+    pub suspended: bool,
+    #[serde(default)]
+    pub devices: Vec<Device>,
 }
 
 impl User {
-    /// Create a new user from a name and password hash.
+    /// Create a new user from a name and password, which is hashed with the given parameters.
     /// This does not save the user to the database!
-    pub fn create(name: String, password: String) -> Result<Self, String> {
-        let hashed_password = Self::hash_password(&password)?;
+    pub fn create(name: String, password: String, argon2: &Argon2Config) -> Result<Self, String> {
+        let hashed_password = Self::hash_password(&password, argon2)?;
 
         Ok(User {
             id: None,
@@ -42,17 +174,26 @@ impl User {
             preferences: UserPreferences {
                 prefers_darkmode: true,
                 profile_color: ProfileColor::Orange,
+                send_read_receipts: true,
+                is_private: false,
+                topics: vec![],
+                expanded_warnings: vec![],
+                hidden_warnings: vec![],
+                wellbeing: WellbeingPreferences::default(),
             },
+            role: Role::User,
+            suspended: false,
+            devices: vec![],
         })
     }
 
-    pub fn hash_password(password: &str) -> Result<String, String> {
+    pub fn hash_password(password: &str, argon2: &Argon2Config) -> Result<String, String> {
         let argon2_config = Config {
             variant: Variant::Argon2id,
             version: Version::Version13,
-            mem_cost: 65536,
-            time_cost: 10,
-            lanes: 4,
+            mem_cost: argon2.mem_cost,
+            time_cost: argon2.time_cost,
+            lanes: argon2.lanes,
             thread_mode: ThreadMode::Parallel,
             secret: &[],
             ad: &[],
@@ -73,6 +214,25 @@ impl User {
 
         hashed_password
     }
+
+    /// Check if the user may see the hidden posts of an author. Only the author and moderators can.
+    pub fn sees_hidden_posts_of(&self, author: &ObjectId) -> bool {
+        self.id == Some(*author) || self.role >= Role::Moderator
+    }
+}
+
+/// A saved user with the given role, whose password is hashed cheaply so that tests stay fast.
+#[cfg(test)]
+pub fn test_user(name: &str, role: Role) -> User {
+    let argon2 = Argon2Config {
+        mem_cost: 8,
+        time_cost: 1,
+        lanes: 1,
+    };
+    let mut user = User::create(name.to_string(), "password".to_string(), &argon2).unwrap();
+    user.id = Some(ObjectId::new());
+    user.role = role;
+    user
 }
 
 #[cfg(test)]
@@ -85,7 +245,60 @@ mod tests {
         let user = User::create(
             "Cat_Lover_84".to_string(),
             "This is a hashed password!".to_string(),
+            &Argon2Config::default(),
         );
         println!("Created the following: {:#?}", user);
     }
+
+    /// The guards rely on the order of the roles, so that e.g. admins pass the moderator guard.
+    #[test]
+    fn roles_are_ordered() {
+        assert!(Role::User < Role::Moderator);
+        assert!(Role::Moderator < Role::Admin);
+        assert_eq!(Role::default(), Role::User);
+    }
+
+    /// Each warning has exactly one preference, and changing it replaces the old one.
+    #[test]
+    fn change_warning_preference() {
+        let mut preferences = UserPreferences {
+            prefers_darkmode: true,
+            profile_color: ProfileColor::Orange,
+            send_read_receipts: true,
+            is_private: false,
+            topics: vec![],
+            expanded_warnings: vec![],
+            hidden_warnings: vec![],
+            wellbeing: WellbeingPreferences::default(),
+        };
+        assert_eq!(
+            preferences.warning_preference(ContentWarning::Grief),
+            WarningPreference::Collapse
+        );
+
+        preferences.set_warning_preference(ContentWarning::Grief, WarningPreference::Hide);
+        preferences.set_warning_preference(ContentWarning::Grief, WarningPreference::Expand);
+        assert_eq!(
+            preferences.warning_preference(ContentWarning::Grief),
+            WarningPreference::Expand
+        );
+        assert!(preferences.hidden_warnings.is_empty());
+
+        preferences.set_warning_preference(ContentWarning::Grief, WarningPreference::Collapse);
+        assert!(preferences.expanded_warnings.is_empty());
+    }
+
+    #[test]
+    fn quiet_hours_wrap_around_midnight() {
+        let night = QuietHours { start: 22, end: 7 };
+        assert!(night.contains(23));
+        assert!(night.contains(0));
+        assert!(!night.contains(7));
+        assert!(!night.contains(12));
+
+        let afternoon = QuietHours { start: 13, end: 15 };
+        assert!(afternoon.contains(14));
+        assert!(!afternoon.contains(15));
+        assert!(!afternoon.contains(22));
+    }
 }
@@ -0,0 +1,93 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use mongodb::bson::oid::ObjectId;
+
+/// Limits how often each user may do something within a window of time, e.g. to stop them from scraping.
+/// The attempts are only kept in memory, so they are forgotten when Bread restarts.
+pub struct RateLimiter {
+    limit: usize,
+    window: Duration,
+    attempts: Mutex<Attempts>,
+}
+
+/// The times of each user's recent attempts, oldest first.
+struct Attempts {
+    by_user: HashMap<ObjectId, VecDeque<Instant>>,
+    last_cleanup: Instant,
+}
+
+impl RateLimiter {
+    /// Create a limiter that allows `limit` attempts per user within any `window`.
+    pub fn new(limit: usize, window: Duration) -> Self {
+        RateLimiter {
+            limit,
+            window,
+            attempts: Mutex::new(Attempts {
+                by_user: HashMap::new(),
+                last_cleanup: Instant::now(),
+            }),
+        }
+    }
+
+    /// Record an attempt by a user. Returns false, without recording the attempt, if the user has made too many.
+    pub fn try_attempt(&self, user: &ObjectId) -> bool {
+        self.try_attempt_at(user, Instant::now())
+    }
+
+    fn try_attempt_at(&self, user: &ObjectId, now: Instant) -> bool {
+        // A poisoned lock only means that another thread panicked while holding it, the attempts are still usable.
+        let mut attempts = self
+            .attempts
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let window = self.window;
+        let is_recent = |attempt: &Instant| now.saturating_duration_since(*attempt) < window;
+
+        // Forget the users who haven't tried anything for a while, so that the map doesn't keep growing.
+        if now.saturating_duration_since(attempts.last_cleanup) >= window {
+            attempts
+                .by_user
+                .retain(|_, times| times.back().is_some_and(is_recent));
+            attempts.last_cleanup = now;
+        }
+
+        let times = attempts.by_user.entry(*user).or_default();
+        while times.front().is_some_and(|attempt| !is_recent(attempt)) {
+            times.pop_front();
+        }
+
+        if times.len() >= self.limit {
+            return false;
+        }
+
+        times.push_back(now);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attempts_are_limited_per_window() {
+        let limiter = RateLimiter::new(2, Duration::from_secs(60));
+        let user = ObjectId::new();
+        let start = Instant::now();
+
+        assert!(limiter.try_attempt_at(&user, start));
+        assert!(limiter.try_attempt_at(&user, start + Duration::from_secs(1)));
+        assert!(!limiter.try_attempt_at(&user, start + Duration::from_secs(2)));
+
+        // Other users have their own limit.
+        assert!(limiter.try_attempt_at(&ObjectId::new(), start + Duration::from_secs(2)));
+
+        // The first attempt no longer counts once the window has passed it.
+        assert!(limiter.try_attempt_at(&user, start + Duration::from_secs(60)));
+        assert!(!limiter.try_attempt_at(&user, start + Duration::from_secs(60)));
+    }
+}
@@ -0,0 +1,91 @@
+use std::{fmt, process::ExitCode};
+
+use rocket::figment::Figment;
+
+use crate::{
+    config::{self, BreadConfig},
+    database::DatabaseHandler,
+    logging,
+    storage::ImageStorage,
+};
+
+/// Why Bread couldn't start.
+#[derive(Debug)]
+pub enum StartupError {
+    Config(String),
+    Database(String),
+    Migration(String),
+    ImageStorage(String),
+}
+
+impl StartupError {
+    /// The exit codes follow `sysexits.h`, so that whatever started Bread can tell the failures apart.
+    pub fn exit_code(&self) -> ExitCode {
+        match self {
+            StartupError::Config(_) => ExitCode::from(78),
+            StartupError::Database(_) => ExitCode::from(69),
+            StartupError::Migration(_) => ExitCode::from(65),
+            StartupError::ImageStorage(_) => ExitCode::from(73),
+        }
+    }
+}
+
+impl fmt::Display for StartupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StartupError::Config(err) => write!(f, "Invalid configuration: {}", err),
+            StartupError::Database(err) => write!(f, "Could not connect to the database: {}", err),
+            StartupError::Migration(err) => write!(f, "Could not migrate the database: {}", err),
+            StartupError::ImageStorage(err) => {
+                write!(f, "Could not create the image directory: {}", err)
+            }
+        }
+    }
+}
+
+/// Set up logging and read the configuration. Logging is set up first, so that reading the configuration can log.
+pub fn load_config() -> Result<(Figment, BreadConfig), StartupError> {
+    let figment = config::figment();
+    logging::init(&figment);
+    let config = BreadConfig::from_figment(&figment).map_err(StartupError::Config)?;
+
+    Ok((figment, config))
+}
+
+/// Like `load_config`, but without requiring a `jwt_secret`, for commands that don't serve Bread.
+pub fn load_tool_config() -> Result<BreadConfig, StartupError> {
+    let figment = config::figment();
+    logging::init(&figment);
+
+    BreadConfig::from_figment_without_secret(&figment).map_err(StartupError::Config)
+}
+
+/// Connect to the configured database.
+pub fn connect_database(config: &BreadConfig) -> Result<DatabaseHandler, StartupError> {
+    DatabaseHandler::create_connection(&config.database_name).map_err(StartupError::Database)
+}
+
+/// Open the configured image storage, creating its directory if needed.
+pub fn open_image_storage(config: &BreadConfig) -> Result<ImageStorage, StartupError> {
+    ImageStorage::new(config.image_dir.clone())
+        .map_err(|err| StartupError::ImageStorage(err.to_string()))
+}
+
+/// Apply the pending database migrations and print which were applied, for the `migrate` commands.
+pub fn migrate() -> Result<(), StartupError> {
+    let config = load_tool_config()?;
+    let db = connect_database(&config)?;
+    let applied = db.run_migrations().map_err(StartupError::Migration)?;
+
+    if applied.is_empty() {
+        println!("The database is already up to date.");
+    }
+    for migration in applied {
+        println!(
+            "Applied migration {} ({}).",
+            migration.version, migration.name
+        );
+    }
+
+    Ok(())
+}
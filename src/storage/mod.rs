@@ -0,0 +1,72 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use mongodb::bson::oid::ObjectId;
+use rocket::{fs::TempFile, http::ContentType};
+
+/// Stores the images of posts as files in a directory.
+/// The images are served on `/images`, and posts refer to them by file name.
+#[derive(Clone)]
+pub struct ImageStorage {
+    dir: PathBuf,
+}
+
+impl ImageStorage {
+    /// Create a storage in a directory. The directory is created if it doesn't exist.
+    pub fn new(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+        Ok(ImageStorage { dir })
+    }
+
+    /// The directory that the images are stored in.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Save an uploaded image. Returns the file name that the image was saved as.
+    pub async fn save(&self, image: &mut TempFile<'_>) -> Result<String, String> {
+        let extension = match image.content_type() {
+            Some(content_type) if content_type == &ContentType::PNG => "png",
+            Some(content_type) if content_type == &ContentType::JPEG => "jpg",
+            Some(content_type) if content_type == &ContentType::GIF => "gif",
+            Some(content_type) if content_type == &ContentType::WEBP => "webp",
+            _ => return Err("Images must be PNG, JPEG, GIF or WebP!".to_string()),
+        };
+
+        let name = format!("{}.{}", ObjectId::new(), extension);
+        image
+            .move_copy_to(self.dir.join(&name))
+            .await
+            .map_err(|err| err.to_string())?;
+
+        Ok(name)
+    }
+
+    /// Check that images can be saved, by writing and deleting a file.
+    /// The file name starts with a dot, so it can never be mistaken for an image.
+    pub fn check_writable(&self) -> Result<(), String> {
+        let path = self.dir.join(format!(".write-check-{}", ObjectId::new()));
+        fs::write(&path, b"bread").map_err(|err| err.to_string())?;
+        fs::remove_file(&path).map_err(|err| err.to_string())
+    }
+
+    /// The path of a stored image. Only files directly inside the storage directory are images.
+    pub fn path(&self, name: &str) -> Result<PathBuf, String> {
+        if name.contains(['/', '\\']) || name.starts_with('.') {
+            return Err("Invalid image name!".to_string());
+        }
+
+        Ok(self.dir.join(name))
+    }
+
+    /// Delete a stored image. Deleting an image that doesn't exist does nothing.
+    pub fn delete(&self, name: &str) -> Result<(), String> {
+        match fs::remove_file(self.path(name)?) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err.to_string()),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use std::time::Duration;
+
+use rocket::{
+    fairing::AdHoc,
+    tokio::{self, time},
+};
+
+use crate::{database::DatabaseHandler, storage::ImageStorage};
+
+/// How often expired posts are looked for.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Delete all expired posts along with their images.
+/// Returns how many posts were deleted. An image that can't be deleted is logged and doesn't stop the sweep.
+pub fn sweep_expired_posts(db: &DatabaseHandler, storage: &ImageStorage) -> Result<usize, String> {
+    let expired_posts = db.find_expired_posts()?;
+
+    for post in &expired_posts {
+        if let Some(id) = &post.id {
+            db.remove_post(id)?;
+        }
+
+        if let Some(image) = &post.image {
+            if let Err(err) = storage.delete(image) {
+                error!("Could not delete image {} of expired post: {}", image, err);
+            }
+        }
+    }
+
+    Ok(expired_posts.len())
+}
+
+/// A fairing that deletes expired posts in the background while Bread is running.
+/// Expired posts are already left out of all queries, so this only frees up space.
+pub fn expired_post_sweeper() -> AdHoc {
+    AdHoc::on_liftoff("Expired post sweeper", |rocket| {
+        Box::pin(async move {
+            let (db, storage) = match (
+                rocket.state::<DatabaseHandler>(),
+                rocket.state::<ImageStorage>(),
+            ) {
+                (Some(db), Some(storage)) => (db.clone(), storage.clone()),
+                _ => {
+                    error!("The expired post sweeper needs a database and image storage!");
+                    return;
+                }
+            };
+            let mut shutdown = rocket.shutdown();
+
+            tokio::spawn(async move {
+                let mut interval = time::interval(SWEEP_INTERVAL);
+
+                loop {
+                    tokio::select! {
+                        _ = interval.tick() => {}
+                        _ = &mut shutdown => break,
+                    }
+
+                    let (db, storage) = (db.clone(), storage.clone());
+                    let result =
+                        tokio::task::spawn_blocking(move || sweep_expired_posts(&db, &storage))
+                            .await;
+
+                    match result {
+                        Ok(Ok(0)) => {}
+                        Ok(Ok(count)) => info!("Deleted {} expired posts.", count),
+                        Ok(Err(err)) => error!("Could not delete expired posts: {}", err),
+                        Err(err) => error!("Could not delete expired posts: {}", err),
+                    }
+                }
+            });
+        })
+    })
+}
@@ -0,0 +1,13 @@
+// Dates are rendered in UTC on the server, since it doesn't know the viewer's time zone.
+// This replaces them with the date in the viewer's own time zone.
+const pad = number => String(number).padStart(2, "0")
+
+document.querySelectorAll("time[datetime]").forEach(element => {
+    const date = new Date(element.getAttribute("datetime"))
+    if (isNaN(date)) {
+        return
+    }
+
+    element.textContent = `${date.getFullYear()}-${pad(date.getMonth() + 1)}-${pad(date.getDate())} ${pad(date.getHours())}:${pad(date.getMinutes())}`
+    element.title = element.getAttribute("datetime")
+})
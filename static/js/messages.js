@@ -0,0 +1,134 @@
+// Messages are end-to-end encrypted: they are encrypted in the browser for every device in the
+// conversation, so the server only ever sees ciphertext.
+//
+// Each device has an ECDH P-256 key pair, and its private key never leaves this browser's IndexedDB.
+// A message is encrypted for a device with AES-GCM, using a key derived from a fresh key pair and the
+// device's public key. The ciphertext holds the fresh public key, the IV and the encrypted message.
+const ECDH = { name: "ECDH", namedCurve: "P-256" }
+// The length of a P-256 public key in the SPKI format.
+const PUBLIC_KEY_LENGTH = 91
+const IV_LENGTH = 12
+
+const toBase64 = buffer => btoa(Array.from(new Uint8Array(buffer), byte => String.fromCharCode(byte)).join(""))
+const fromBase64 = text => Uint8Array.from(atob(text), char => char.charCodeAt(0))
+
+const concat = (...buffers) => {
+    const bytes = new Uint8Array(buffers.reduce((length, buffer) => length + buffer.byteLength, 0))
+    let offset = 0
+    for (const buffer of buffers) {
+        bytes.set(new Uint8Array(buffer), offset)
+        offset += buffer.byteLength
+    }
+    return bytes
+}
+
+const request = async (method, url, body) => {
+    const response = await fetch(url, { method, body })
+    if (!response.ok) {
+        throw new Error(await response.text())
+    }
+    return response
+}
+
+// Run a request against the object store that holds this device's id and keys.
+const deviceStore = (mode, action) => new Promise((resolve, reject) => {
+    const open = indexedDB.open("bread", 1)
+    open.onupgradeneeded = () => open.result.createObjectStore("device")
+    open.onerror = () => reject(open.error)
+    open.onsuccess = () => {
+        const result = action(open.result.transaction("device", mode).objectStore("device"))
+        result.onsuccess = () => resolve(result.result)
+        result.onerror = () => reject(result.error)
+    }
+})
+
+// Get this device's keys, creating them the first time, and make sure the server has its public key.
+const getDevice = async () => {
+    let device = await deviceStore("readonly", store => store.get("self"))
+    if (!device) {
+        // The private key can't be exported, not even by this script.
+        const keyPair = await crypto.subtle.generateKey(ECDH, false, ["deriveKey"])
+        device = { id: crypto.randomUUID(), keyPair }
+        await deviceStore("readwrite", store => store.put(device, "self"))
+    }
+
+    const publicKey = toBase64(await crypto.subtle.exportKey("spki", device.keyPair.publicKey))
+    await request("PUT", `/api/devices/${device.id}`, new URLSearchParams({ public_key: publicKey }))
+    return device
+}
+
+const deriveKey = (privateKey, publicKey, usage) => crypto.subtle.deriveKey(
+    { name: "ECDH", public: publicKey },
+    privateKey,
+    { name: "AES-GCM", length: 256 },
+    false,
+    [usage],
+)
+
+const encrypt = async (text, publicKey) => {
+    const deviceKey = await crypto.subtle.importKey("spki", fromBase64(publicKey), ECDH, false, [])
+    const ephemeral = await crypto.subtle.generateKey(ECDH, true, ["deriveKey"])
+    const key = await deriveKey(ephemeral.privateKey, deviceKey, "encrypt")
+    const iv = crypto.getRandomValues(new Uint8Array(IV_LENGTH))
+    const ciphertext = await crypto.subtle.encrypt({ name: "AES-GCM", iv }, key, new TextEncoder().encode(text))
+
+    return toBase64(concat(await crypto.subtle.exportKey("spki", ephemeral.publicKey), iv, ciphertext))
+}
+
+const decrypt = async (ciphertext, privateKey) => {
+    const bytes = fromBase64(ciphertext)
+    const ephemeralKey = await crypto.subtle.importKey("spki", bytes.slice(0, PUBLIC_KEY_LENGTH), ECDH, false, [])
+    const key = await deriveKey(privateKey, ephemeralKey, "decrypt")
+    const iv = bytes.slice(PUBLIC_KEY_LENGTH, PUBLIC_KEY_LENGTH + IV_LENGTH)
+    const text = await crypto.subtle.decrypt({ name: "AES-GCM", iv }, key, bytes.slice(PUBLIC_KEY_LENGTH + IV_LENGTH))
+
+    return new TextDecoder().decode(text)
+}
+
+const form = document.querySelector(".message-form")
+const error = form.querySelector(".error")
+const showError = message => {
+    error.textContent = message
+    error.hidden = false
+}
+
+getDevice().then(device => {
+    document.querySelectorAll(".message > .content").forEach(async element => {
+        const envelope = JSON.parse(element.dataset.envelopes).find(envelope => envelope.device === device.id)
+        if (!envelope) {
+            element.textContent = "This message was sent before this device was set up, so it can't be read here."
+            return
+        }
+
+        try {
+            element.textContent = await decrypt(envelope.ciphertext, device.keyPair.privateKey)
+        } catch {
+            element.textContent = "This message couldn't be decrypted."
+        }
+    })
+
+    form.addEventListener("submit", async event => {
+        event.preventDefault()
+        const text = form.querySelector("textarea").value.trim()
+        if (!text) {
+            return
+        }
+
+        try {
+            // Encrypt the message for the recipient's devices and the sender's own.
+            const keys = await Promise.all([form.dataset.with, form.dataset.me].map(async user => {
+                return (await request("GET", `/api/keys/${user}`)).json()
+            }))
+            const body = new URLSearchParams()
+            for (const [i, { device, public_key }] of keys.flat().entries()) {
+                body.append(`envelopes[${i}].device`, device)
+                body.append(`envelopes[${i}].ciphertext`, await encrypt(text, public_key))
+            }
+
+            await request("POST", `/api/conversations/${form.dataset.conversation}/messages`, body)
+            location.reload()
+        } catch (err) {
+            showError(err.message)
+        }
+    })
+}).catch(err => showError(`Couldn't set up encryption on this device: ${err.message}`))
@@ -0,0 +1,5 @@
+// The server needs the viewer's time zone to know when their days and quiet hours start.
+// getTimezoneOffset is how far UTC is ahead of local time, so it is flipped.
+document.querySelectorAll("input[name=utc_offset_minutes]").forEach(input => {
+    input.value = -new Date().getTimezoneOffset()
+})